@@ -1,17 +1,17 @@
 // Based on code from https://github.com/sharkdp/bat a1b9334a44a2c652f52dddaa83dbacba57372468
 // See src/bat/LICENSE
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use ansi_term::Colour::Green;
 use ansi_term::Style;
-use syntect::dumps::{from_binary, from_reader};
+use syntect::dumps::{dump_to_file, from_binary, from_reader};
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 
-use crate::bat::dirs::PROJECT_DIRS;
+use crate::bat::dirs::{DELTA_PROJECT_DIRS, PROJECT_DIRS};
 use crate::errors::*;
 
 pub struct HighlightingAssets {
@@ -19,9 +19,77 @@ pub struct HighlightingAssets {
     pub theme_set: ThemeSet,
 }
 
+impl Default for HighlightingAssets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HighlightingAssets {
+    /// Load the assets from delta's cache, or else from bat's cache, or else use the assets
+    /// included in the delta binary.
     pub fn new() -> Self {
-        Self::from_cache().unwrap_or_else(|_| Self::from_binary())
+        Self::from_cache(DELTA_PROJECT_DIRS.cache_dir())
+            .or_else(|_| Self::from_cache(PROJECT_DIRS.cache_dir()))
+            .unwrap_or_else(|_| Self::from_binary())
+    }
+
+    /// Compile the assets included in the delta binary together with the syntax definitions
+    /// (.sublime-syntax files) in the syntaxes subdirectory of `source_dir` and the themes
+    /// (.tmTheme files) in its themes subdirectory.
+    pub fn from_files(source_dir: &Path) -> Result<Self> {
+        let mut theme_set = Self::get_integrated_themeset();
+        let theme_dir = source_dir.join("themes");
+        if theme_dir.exists() {
+            theme_set.add_from_folder(&theme_dir).chain_err(|| {
+                format!(
+                    "Could not load themes from '{}'",
+                    theme_dir.to_string_lossy()
+                )
+            })?;
+        }
+
+        let mut syntax_set_builder = Self::get_integrated_syntaxset().into_builder();
+        let syntax_dir = source_dir.join("syntaxes");
+        if syntax_dir.exists() {
+            syntax_set_builder
+                .add_from_folder(&syntax_dir, true)
+                .chain_err(|| {
+                    format!(
+                        "Could not load syntax definitions from '{}'",
+                        syntax_dir.to_string_lossy()
+                    )
+                })?;
+        }
+
+        Ok(HighlightingAssets {
+            syntax_set: syntax_set_builder.build(),
+            theme_set,
+        })
+    }
+
+    pub fn save_to_cache(&self, target_dir: &Path) -> Result<()> {
+        fs::create_dir_all(target_dir).chain_err(|| {
+            format!(
+                "Could not create cache directory '{}'",
+                target_dir.to_string_lossy()
+            )
+        })?;
+        let theme_set_path = theme_set_path(target_dir);
+        dump_to_file(&self.theme_set, &theme_set_path).chain_err(|| {
+            format!(
+                "Could not save theme set to '{}'",
+                theme_set_path.to_string_lossy()
+            )
+        })?;
+        let syntax_set_path = syntax_set_path(target_dir);
+        dump_to_file(&self.syntax_set, &syntax_set_path).chain_err(|| {
+            format!(
+                "Could not save syntax set to '{}'",
+                syntax_set_path.to_string_lossy()
+            )
+        })?;
+        Ok(())
     }
 
     fn get_integrated_syntaxset() -> SyntaxSet {
@@ -32,12 +100,13 @@ impl HighlightingAssets {
         from_binary(include_bytes!("../../assets/themes.bin"))
     }
 
-    fn from_cache() -> Result<Self> {
-        let theme_set_path = theme_set_path();
-        let syntax_set_file = File::open(&syntax_set_path()).chain_err(|| {
+    fn from_cache(cache_dir: &Path) -> Result<Self> {
+        let theme_set_path = theme_set_path(cache_dir);
+        let syntax_set_path = syntax_set_path(cache_dir);
+        let syntax_set_file = File::open(&syntax_set_path).chain_err(|| {
             format!(
                 "Could not load cached syntax set '{}'",
-                syntax_set_path().to_string_lossy()
+                syntax_set_path.to_string_lossy()
             )
         })?;
         let syntax_set: SyntaxSet = from_reader(BufReader::new(syntax_set_file))
@@ -58,7 +127,7 @@ impl HighlightingAssets {
         })
     }
 
-    fn from_binary() -> Self {
+    pub(crate) fn from_binary() -> Self {
         let syntax_set = Self::get_integrated_syntaxset();
         let theme_set = Self::get_integrated_themeset();
 
@@ -69,12 +138,23 @@ impl HighlightingAssets {
     }
 }
 
-fn theme_set_path() -> PathBuf {
-    PROJECT_DIRS.cache_dir().join("themes.bin")
+fn theme_set_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("themes.bin")
 }
 
-fn syntax_set_path() -> PathBuf {
-    PROJECT_DIRS.cache_dir().join("syntaxes.bin")
+fn syntax_set_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("syntaxes.bin")
+}
+
+/// Remove the files that `save_to_cache` writes to `cache_dir`.
+pub fn clear_cache(cache_dir: &Path) -> Result<()> {
+    for path in &[theme_set_path(cache_dir), syntax_set_path(cache_dir)] {
+        if path.exists() {
+            fs::remove_file(path)
+                .chain_err(|| format!("Could not remove '{}'", path.to_string_lossy()))?;
+        }
+    }
+    Ok(())
 }
 
 pub fn list_languages() -> std::io::Result<()> {
@@ -142,3 +222,50 @@ pub fn list_languages() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    const SYNTAX: &str = "\
+%YAML 1.2
+---
+name: Delta Test Language
+file_extensions: [deltatest]
+scope: source.deltatest
+contexts:
+  main:
+    - match: '\\bdelta\\b'
+      scope: keyword.control.deltatest
+";
+
+    #[test]
+    fn test_user_syntax_is_loaded_from_cache() {
+        let dir = std::env::temp_dir().join(format!("delta-test-assets-{}", std::process::id()));
+        let source_dir = dir.join("config");
+        let cache_dir = dir.join("cache");
+        fs::create_dir_all(source_dir.join("syntaxes")).unwrap();
+        fs::write(
+            source_dir.join("syntaxes").join("deltatest.sublime-syntax"),
+            SYNTAX,
+        )
+        .unwrap();
+
+        HighlightingAssets::from_files(&source_dir)
+            .unwrap()
+            .save_to_cache(&cache_dir)
+            .unwrap();
+        let assets = HighlightingAssets::from_cache(&cache_dir).unwrap();
+        assert!(assets
+            .syntax_set
+            .find_syntax_by_extension("deltatest")
+            .is_some());
+        assert!(assets.syntax_set.find_syntax_by_extension("rs").is_some());
+
+        clear_cache(&cache_dir).unwrap();
+        assert!(HighlightingAssets::from_cache(&cache_dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
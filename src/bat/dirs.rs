@@ -13,10 +13,11 @@ use std::env;
 /// checked first. The fallback directories are `~/.cache/bat` and `~/.config/bat`, respectively.
 pub struct BatProjectDirs {
     cache_dir: PathBuf,
+    config_dir: PathBuf,
 }
 
 impl BatProjectDirs {
-    fn new() -> Option<BatProjectDirs> {
+    fn new(name: &str) -> Option<BatProjectDirs> {
         #[cfg(target_os = "macos")]
         let cache_dir_op = env::var_os("XDG_CACHE_HOME")
             .map(PathBuf::from)
@@ -26,17 +27,40 @@ impl BatProjectDirs {
         #[cfg(not(target_os = "macos"))]
         let cache_dir_op = dirs_rs::cache_dir();
 
-        let cache_dir = cache_dir_op.map(|d| d.join("bat"))?;
+        let cache_dir = cache_dir_op.map(|d| d.join(name))?;
 
-        Some(BatProjectDirs { cache_dir })
+        #[cfg(target_os = "macos")]
+        let config_dir_op = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| dirs_rs::home_dir().map(|d| d.join(".config")));
+
+        #[cfg(not(target_os = "macos"))]
+        let config_dir_op = dirs_rs::config_dir();
+
+        let config_dir = config_dir_op.map(|d| d.join(name))?;
+
+        Some(BatProjectDirs {
+            cache_dir,
+            config_dir,
+        })
     }
 
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
 }
 
 lazy_static! {
+    /// bat's directories, whose cache delta reads if it has none of its own.
     pub static ref PROJECT_DIRS: BatProjectDirs =
-        BatProjectDirs::new().expect("Could not get home directory");
+        BatProjectDirs::new("bat").expect("Could not get home directory");
+    /// Delta's directories: user syntax definitions and themes are read from the syntaxes and
+    /// themes subdirectories of the config directory, and compiled into the cache directory.
+    pub static ref DELTA_PROJECT_DIRS: BatProjectDirs =
+        BatProjectDirs::new("delta").expect("Could not get home directory");
 }
@@ -1,12 +1,15 @@
 use std::process;
 
 use structopt::clap::AppSettings::{ColorAlways, ColoredHelp, DeriveDisplayOrder};
+use structopt::clap::ArgMatches;
 use structopt::StructOpt;
 
 use crate::bat::assets::HighlightingAssets;
 use crate::bat::output::PagingMode;
-use crate::config;
+use crate::config::{self, ColorDepth};
 use crate::env;
+use crate::features;
+use crate::git_config::GitConfig;
 use crate::rewrite;
 use crate::theme;
 
@@ -61,7 +64,9 @@ A complete description of the style string syntax follows:
   color.
 
 - If you want delta to choose one of the colors automatically, then use the special color 'auto'.
-  This can be used for both foreground and background.
+  This can be used for both foreground and background. The automatic background colors of minus
+  and plus lines are tints of the syntax theme's background, in the theme's own colors for deleted
+  and inserted text if it has them. Use --show-background-colors to see them.
 
 - If you want the foreground/background color to be your terminal's foreground/background color,
   then use the special color 'normal'.
@@ -112,6 +117,52 @@ within a style string):
    Specifying colors like this is useful if your terminal only supports 256 colors (i.e. doesn\'t
    support 24-bit color).
 
+LINE NUMBERS
+------------
+
+Options --line-numbers-left-format and --line-numbers-right-format allow you to change the contents
+of the line number gutter. The two format strings are displayed side by side, the left one first.
+In a format string, the placeholders {nm} and {np} are replaced by the line number in the minus
+file (old version) and the plus file (new version) respectively. A placeholder may specify an
+alignment and a minimum width, e.g. {nm:^4}, where the alignment is one of '<' (left), '^'
+(center) and '>' (right, the default). The width is increased as necessary to fit the largest line
+number in the hunk. Text outside the placeholders is displayed as it is, in the style given by
+--line-numbers-left-style or --line-numbers-right-style.
+
+GIT CONFIG
+----------
+
+Options can also be set in a [delta] section of your git config files (system, global, or that of
+the repository), using the long option names as keys. Options given on the command line take
+precedence. For example:
+
+[delta]
+    side-by-side = true
+    minus-style = red bold
+
+Since git config keys cannot start with a digit, --24-bit-color is set with the key 'true-color'.
+
+FEATURES
+--------
+
+A feature is a named set of option values, defined in a subsection of the [delta] section. To keep
+presets in a separate file, put them there and include it from your git config with git's
+[include] path = ... directive. For example:
+
+[delta \"presentation\"]
+    side-by-side = true
+    line-numbers = true
+    minus-style = bold red
+
+[delta \"dense\"]
+    file-decoration-style = none
+    hunk-header-decoration-style = none
+
+Features are activated with --features, e.g. --features='presentation dense'. If several
+features set an option, the last one wins. Options given on the command line take precedence over
+features, and features take precedence over the [delta] section itself. --color-only is a builtin
+feature; its options can be modified in a [delta \"color-only\"] section.
+
 If something isn't working correctly, or you have a feature request, please open an issue at
 https://github.com/dandavison/delta/issues.
 "
@@ -134,6 +185,14 @@ pub struct Opt {
     #[structopt(long = "dark")]
     pub dark: bool,
 
+    /// Whether to detect if the terminal background is light or dark when none of --light,
+    /// --dark and --theme is given: 'auto' (detect if the output is to a terminal), 'always', or
+    /// 'never'. The background is given by the COLORFGBG environment variable if it is set;
+    /// otherwise the terminal is asked for its background color. If the background is not
+    /// detected, it is assumed to be dark.
+    #[structopt(long = "detect-dark-light", default_value = "auto")]
+    pub detect_dark_light: String,
+
     #[structopt(long = "minus-style", default_value = "normal auto")]
     /// Style (foreground, background, attributes) for removed lines. See STYLES section.
     pub minus_style: String,
@@ -166,6 +225,41 @@ pub struct Opt {
     /// have an emphasized section. Defaults to --plus-style. See STYLES section.
     pub plus_non_emph_style: Option<String>,
 
+    #[structopt(long = "whitespace-error-style", default_value = "reverse red")]
+    /// Style (foreground, background, attributes) for whitespace errors in added lines, as
+    /// detected by git's default `core.whitespace` setting: trailing whitespace (including a
+    /// carriage return), and a space before a tab in the indentation. Use 'omit' to display
+    /// whitespace errors like the rest of the line. See STYLES section.
+    pub whitespace_error_style: String,
+
+    #[structopt(long = "color-moved")]
+    /// Detect blocks of code that have been moved, similar to `git diff --color-moved`. A block
+    /// of removed lines that is added elsewhere in the diff (possibly in a different file) is
+    /// displayed with --moved-from-style, and the added block with --moved-to-style. Lines are
+    /// compared exactly, including whitespace, and blocks with fewer than 20 alphanumeric
+    /// characters are not displayed as moved. Note that the entire input is buffered before
+    /// anything is printed, so output does not start until the diff has been read completely.
+    pub color_moved: bool,
+
+    #[structopt(long = "full-file-highlighting")]
+    /// Read the old and new versions of each file, so that the syntax highlighting of each hunk
+    /// takes account of the code that precedes it: a hunk that starts inside e.g. a block
+    /// comment or multi-line string is then highlighted correctly. For git output the files are
+    /// read with `git cat-file`, using the blob hashes in the index line of the diff, or from the
+    /// working tree; for `diff -u` output they are read from the paths in the diff. This is
+    /// slower, especially for large files with many hunks.
+    pub full_file_highlighting: bool,
+
+    #[structopt(long = "moved-from-style", default_value = "normal auto")]
+    /// Style (foreground, background, attributes) for removed lines that have been moved
+    /// elsewhere. Only used with --color-moved. See STYLES section.
+    pub moved_from_style: String,
+
+    #[structopt(long = "moved-to-style", default_value = "syntax auto")]
+    /// Style (foreground, background, attributes) for added lines that have been moved from
+    /// elsewhere. Only used with --color-moved. See STYLES section.
+    pub moved_to_style: String,
+
     #[structopt(long = "commit-style", default_value = "raw")]
     /// Style (foreground, background, attributes) for the commit hash line. See STYLES section.
     pub commit_style: String,
@@ -197,15 +291,94 @@ pub struct Opt {
 
     #[structopt(long = "color-only")]
     /// Do not alter the input in any way other than applying colors. Equivalent to
-    /// `--keep-plus-minus-markers --width variable --tabs 0 --commit-decoration ''
-    /// --file-decoration '' --hunk-decoration ''`.
+    /// `--features=color-only`, which sets `--keep-plus-minus-markers --tabs 0
+    /// --commit-style raw --commit-decoration-style none --file-style raw
+    /// --file-decoration-style none --hunk-header-style raw --hunk-header-decoration-style none`.
     pub color_only: bool,
 
+    #[structopt(long = "features")]
+    /// Names of features to activate, separated by spaces. A feature is a named set of option
+    /// values, defined in a [delta "name"] section of git config (see FEATURES below). If this
+    /// option is absent, the value of the environment variable DELTA_FEATURES is used, or else
+    /// that of the features key of the [delta] section.
+    pub features: Option<String>,
+
     #[structopt(long = "keep-plus-minus-markers")]
     /// Prefix added/removed lines with a +/- character, respectively, exactly as git does. The
     /// default behavior is to output a space character in place of these markers.
     pub keep_plus_minus_markers: bool,
 
+    #[structopt(long = "side-by-side")]
+    /// Display a side-by-side diff view instead of the traditional view. Removed lines are shown
+    /// in a left panel and added lines in a right panel, with each pair of lines inferred to be
+    /// homologous on the same row. Unchanged lines are shown in both panels. The panels share the
+    /// width given by --width, and lines that do not fit in their panel are truncated.
+    pub side_by_side: bool,
+
+    #[structopt(short = "n", long = "line-numbers")]
+    /// Display line numbers next to the diff. The line numbers of the old and new versions of the
+    /// file are shown in a gutter, formatted according to --line-numbers-left-format and
+    /// --line-numbers-right-format. See the LINE NUMBERS section.
+    pub line_numbers: bool,
+
+    #[structopt(long = "line-numbers-minus-style", default_value = "auto")]
+    /// Style (foreground, background, attributes) for the line numbers of removed lines. See
+    /// STYLES and LINE NUMBERS sections.
+    pub line_numbers_minus_style: String,
+
+    #[structopt(long = "line-numbers-zero-style", default_value = "auto")]
+    /// Style (foreground, background, attributes) for the line numbers of unchanged lines. See
+    /// STYLES and LINE NUMBERS sections.
+    pub line_numbers_zero_style: String,
+
+    #[structopt(long = "line-numbers-plus-style", default_value = "auto")]
+    /// Style (foreground, background, attributes) for the line numbers of added lines. See STYLES
+    /// and LINE NUMBERS sections.
+    pub line_numbers_plus_style: String,
+
+    #[structopt(long = "line-numbers-left-format", default_value = "{nm:^4}⋮")]
+    /// Format string for the left column of the line number gutter. The default displays the line
+    /// number of the minus file (old version), centered in a column of width 4, followed by a
+    /// dividing character. See the LINE NUMBERS section.
+    pub line_numbers_left_format: String,
+
+    #[structopt(long = "line-numbers-right-format", default_value = "{np:^4}│")]
+    /// Format string for the right column of the line number gutter. The default displays the line
+    /// number of the plus file (new version), centered in a column of width 4, followed by a
+    /// dividing character. See the LINE NUMBERS section.
+    pub line_numbers_right_format: String,
+
+    #[structopt(long = "line-numbers-left-style", default_value = "blue")]
+    /// Style (foreground, background, attributes) for the text of the left column of the line
+    /// number gutter, i.e. everything other than the line numbers themselves. See STYLES and LINE
+    /// NUMBERS sections.
+    pub line_numbers_left_style: String,
+
+    #[structopt(long = "line-numbers-right-style", default_value = "blue")]
+    /// Style (foreground, background, attributes) for the text of the right column of the line
+    /// number gutter, i.e. everything other than the line numbers themselves. See STYLES and LINE
+    /// NUMBERS sections.
+    pub line_numbers_right_style: String,
+
+    #[structopt(long = "hyperlinks")]
+    /// Render commit hashes, file names, and the line numbers under hunk headers as hyperlinks,
+    /// using the OSC 8 escape sequence supported by many terminal emulators. See
+    /// --hyperlinks-file-link-format and --hyperlinks-commit-link-format.
+    pub hyperlinks: bool,
+
+    #[structopt(long = "hyperlinks-file-link-format", default_value = "file://{path}")]
+    /// Format string for file hyperlinks. The placeholder {path} is replaced by the absolute path of
+    /// the file, and {line} by the line number (which is empty for file headers). For example, an
+    /// editor might be opened at the changed line by a link such as
+    /// "file-line://{path}:{line}".
+    pub hyperlinks_file_link_format: String,
+
+    #[structopt(long = "hyperlinks-commit-link-format")]
+    /// Format string for commit hyperlinks. The placeholder {commit} is replaced by the commit hash.
+    /// For example: --hyperlinks-commit-link-format='https://git.example.com/cgit/repo/commit/?id={commit}'.
+    /// If this is not supplied, commit hashes are not hyperlinked.
+    pub hyperlinks_commit_link_format: Option<String>,
+
     /// The width of underline/overline decorations. Use --width=variable to extend decorations and
     /// background colors to the end of the text only. Otherwise background colors extend to the
     /// full terminal width.
@@ -219,6 +392,15 @@ pub struct Opt {
     #[structopt(long = "tabs", default_value = "4")]
     pub tab_width: usize,
 
+    /// Map files whose path matches a glob to a syntax, e.g. --map-syntax='*.conf:INI' or
+    /// --map-syntax='Jenkinsfile:Groovy'. The glob is matched against the full path of the file
+    /// and against its file name; '*' does not match '/', but '**' does. The syntax name is as
+    /// listed by --list-languages. This option may be given more than once, in which case later
+    /// rules take precedence. Files that no rule matches are highlighted according to their
+    /// extension.
+    #[structopt(long = "map-syntax", number_of_values = 1)]
+    pub map_syntax: Vec<String>,
+
     /// Show the command-line arguments (RGB hex codes) for the background colors that are in
     /// effect. The hex codes are displayed with their associated background color. This option can
     /// be combined with --light and --dark to view the background colors for those modes. It can
@@ -227,6 +409,11 @@ pub struct Opt {
     #[structopt(long = "show-background-colors")]
     pub show_background_colors: bool,
 
+    /// Show the values of all options, as resolved from the command line, git config, features,
+    /// and environment variables, and exit. Styles are displayed in their own colors.
+    #[structopt(long = "show-config")]
+    pub show_config: bool,
+
     /// List supported languages and associated file extensions.
     #[structopt(long = "list-languages")]
     pub list_languages: bool,
@@ -241,6 +428,9 @@ pub struct Opt {
     #[structopt(long = "list-themes")]
     pub list_themes: bool,
 
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+
     /// The maximum distance between two lines for them to be inferred to be homologous. Homologous
     /// line pairs are highlighted according to the deletion and insertion operations transforming
     /// one into the other.
@@ -251,16 +441,52 @@ pub struct Opt {
     /// "auto" means that delta will emit 24-bit color codes iff the environment variable COLORTERM
     /// has the value "truecolor" or "24bit". If your terminal application (the application you use
     /// to enter commands at a shell prompt) supports 24 bit colors, then it probably already sets
-    /// this environment variable, in which case you don't need to do anything.
+    /// this environment variable, in which case you don't need to do anything. Otherwise, "auto"
+    /// means that the number of colors is detected as for --color-depth.
     #[structopt(long = "24-bit-color", default_value = "auto")]
     pub true_color: String,
 
+    /// Whether to use colors in terminal output: auto, always, or never. "auto" means that colors
+    /// are used unless the NO_COLOR environment variable is set. Without colors, the output keeps
+    /// its layout, and styles use only attributes such as bold, underline, and reverse: the
+    /// emphasized sections of removed and added lines, which are otherwise marked only by their
+    /// background colors, are shown in reverse video, moved lines are underlined, and removed and
+    /// added lines keep their - and + markers.
+    #[structopt(long = "color", default_value = "auto")]
+    pub color: String,
+
+    /// The number of colors to use: 16, 256, or 24bit. Colors that are not available, whether
+    /// they come from the syntax theme or from a style, are replaced by the nearest available
+    /// color; with 16 colors, removed and added lines have red and green backgrounds, and their
+    /// emphasized sections bright red and bright green. If this option is not given, it is
+    /// determined by --24-bit-color, and then by the TERM environment variable and its terminfo
+    /// entry: 16 colors are used only for the Linux console, VT terminals, and terminals with
+    /// fewer than 8 colors; otherwise 256.
+    #[structopt(long = "color-depth")]
+    pub color_depth: Option<String>,
+
     /// Whether to use a pager when displaying output. Options are: auto, always, and never. The
     /// default pager is `less`: this can be altered by setting the environment variables BAT_PAGER
     /// or PAGER (BAT_PAGER has priority).
     #[structopt(long = "paging", default_value = "auto")]
     pub paging_mode: String,
 
+    /// The format of the output. Options are: ansi, html, json, and plain. "ansi" is the usual
+    /// display for a terminal. "html" writes the same display as a self-contained HTML document,
+    /// with styles as inline CSS and the commit, file, and hunk header decorations drawn as CSS
+    /// borders, for pasting into documents and wikis; 24-bit colors are used unless
+    /// --24-bit-color=never. "json" emits a single JSON document describing the commits, files, hunks, and lines of the
+    /// diff, including the inferred pairing of removed and added lines and the emphasized sections
+    /// of each line, for consumption by other programs. "plain" is the usual display without
+    /// colors or other styles.
+    #[structopt(long = "output-format", default_value = "ansi")]
+    pub output_format: String,
+
+    /// With --output-format=json, include for each line its sections together with the syntax
+    /// scopes (e.g. "source.rust storage.type.rust") that apply to them.
+    #[structopt(long = "json-syntax-scopes")]
+    pub json_syntax_scopes: bool,
+
     #[structopt(long = "minus-color")]
     /// Deprecated: use --minus-style='normal my_background_color'.
     pub deprecated_minus_background_color: Option<String>,
@@ -303,6 +529,143 @@ pub struct Opt {
     pub deprecated_hunk_color: Option<String>,
 }
 
+#[derive(StructOpt, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Manage delta's cache of syntax definitions and themes. Delta loads syntax definitions and
+    /// themes from its own cache if it has been built, else from bat's cache, else uses those
+    /// included in the delta binary.
+    Cache(CacheCommand),
+}
+
+#[derive(StructOpt, Clone, Debug, PartialEq)]
+pub enum CacheCommand {
+    /// Compile the syntax definitions (.sublime-syntax files) in the syntaxes subdirectory of
+    /// delta's config directory (e.g. ~/.config/delta/syntaxes) and the themes (.tmTheme files) in
+    /// its themes subdirectory, together with delta's builtin ones, into delta's cache.
+    Build,
+    /// Remove delta's cache, so that the builtin syntax definitions and themes are used.
+    Clear,
+}
+
+impl Opt {
+    /// Parse the command line arguments, taking the value of any option that is not given on the
+    /// command line from the active features or the [delta] section of git config.
+    pub fn from_args_and_git_config(git_config: Option<&GitConfig>) -> Self {
+        let arg_matches = Self::clap().get_matches();
+        let mut opt = Self::from_clap(&arg_matches);
+        set_options(&mut opt, &arg_matches, git_config);
+        opt
+    }
+}
+
+/// For each option not given on the command line, set its value from the active features, or from
+/// the key of the same name in the [delta] section of git config. This happens before the rewrite
+/// rules are applied, so that deprecated options behave the same whichever source their value
+/// came from.
+macro_rules! set_options {
+    ([$( ($option_name:expr, $field_ident:ident) ),* $(,)?],
+     $opt:expr, $arg_matches:expr, $features:expr, $git_config:expr) => {
+        $(
+            // structopt names each argument after its field, in kebab-case.
+            let arg_name = stringify!($field_ident).replace('_', "-");
+            if $arg_matches.occurrences_of(&arg_name) == 0 {
+                if let Some(value) =
+                    features::get_option_value($option_name, $features, $git_config)
+                {
+                    $opt.$field_ident = value;
+                }
+            }
+        )*
+    };
+}
+
+pub fn set_options(opt: &mut Opt, arg_matches: &ArgMatches, git_config: Option<&GitConfig>) {
+    if arg_matches.occurrences_of("color-only") == 0 {
+        if let Some(color_only) =
+            git_config.and_then(|git_config| git_config.get("delta.color-only"))
+        {
+            opt.color_only = color_only;
+        }
+    }
+    let features = features::get_features(
+        if arg_matches.occurrences_of("features") > 0 {
+            opt.features.as_deref()
+        } else {
+            None
+        },
+        opt.color_only,
+        git_config,
+    );
+    set_options!(
+        [
+            ("color", color),
+            ("color-depth", color_depth),
+            ("color-moved", color_moved),
+            ("commit-decoration-style", commit_decoration_style),
+            ("commit-style", commit_style),
+            ("dark", dark),
+            ("detect-dark-light", detect_dark_light),
+            ("file-decoration-style", file_decoration_style),
+            ("file-style", file_style),
+            ("full-file-highlighting", full_file_highlighting),
+            ("hunk-header-decoration-style", hunk_header_decoration_style),
+            ("hunk-header-style", hunk_header_style),
+            ("hyperlinks", hyperlinks),
+            (
+                "hyperlinks-commit-link-format",
+                hyperlinks_commit_link_format
+            ),
+            ("hyperlinks-file-link-format", hyperlinks_file_link_format),
+            ("json-syntax-scopes", json_syntax_scopes),
+            ("keep-plus-minus-markers", keep_plus_minus_markers),
+            ("light", light),
+            ("line-numbers", line_numbers),
+            ("line-numbers-left-format", line_numbers_left_format),
+            ("line-numbers-left-style", line_numbers_left_style),
+            ("line-numbers-minus-style", line_numbers_minus_style),
+            ("line-numbers-plus-style", line_numbers_plus_style),
+            ("line-numbers-right-format", line_numbers_right_format),
+            ("line-numbers-right-style", line_numbers_right_style),
+            ("line-numbers-zero-style", line_numbers_zero_style),
+            ("map-syntax", map_syntax),
+            ("max-line-distance", max_line_distance),
+            ("minus-emph-style", minus_emph_style),
+            ("minus-non-emph-style", minus_non_emph_style),
+            ("minus-style", minus_style),
+            ("moved-from-style", moved_from_style),
+            ("moved-to-style", moved_to_style),
+            ("output-format", output_format),
+            ("paging", paging_mode),
+            ("plus-emph-style", plus_emph_style),
+            ("plus-non-emph-style", plus_non_emph_style),
+            ("plus-style", plus_style),
+            ("side-by-side", side_by_side),
+            ("tabs", tab_width),
+            ("theme", theme),
+            // git config keys cannot start with a digit, so --24-bit-color cannot be used.
+            ("true-color", true_color),
+            ("whitespace-error-style", whitespace_error_style),
+            ("width", width),
+            ("zero-style", zero_style),
+            // Deprecated
+            ("commit-color", deprecated_commit_color),
+            ("file-color", deprecated_file_color),
+            ("highlight-removed", deprecated_highlight_minus_lines),
+            ("hunk-color", deprecated_hunk_color),
+            ("hunk-style", deprecated_hunk_style),
+            ("minus-color", deprecated_minus_background_color),
+            ("minus-emph-color", deprecated_minus_emph_background_color),
+            ("plus-color", deprecated_plus_background_color),
+            ("plus-emph-color", deprecated_plus_emph_background_color),
+        ],
+        opt,
+        arg_matches,
+        &features,
+        git_config
+    );
+    opt.features = Some(features.join(" "));
+}
+
 pub fn process_command_line_arguments<'a>(mut opt: Opt) -> config::Config<'a> {
     let assets = HighlightingAssets::new();
 
@@ -323,24 +686,41 @@ pub fn process_command_line_arguments<'a>(mut opt: Opt) -> config::Config<'a> {
         }
     };
 
-    let true_color = match opt.true_color.as_ref() {
-        "always" => true,
-        "never" => false,
-        "auto" => is_truecolor_terminal(),
-        _ => {
+    let color_depth = match opt.color_depth.as_deref() {
+        Some("16") => ColorDepth::Ansi16,
+        Some("256") => ColorDepth::Ansi256,
+        Some("24bit") => ColorDepth::TrueColor,
+        Some(color_depth) => {
             eprintln!(
-                "Invalid value for --24-bit-color option: {} (valid values are \"always\", \"never\", and \"auto\")",
-                opt.true_color
+                "Invalid value for --color-depth option: {} (valid values are \"16\", \"256\", and \"24bit\")",
+                color_depth
             );
             process::exit(1);
         }
+        None => match opt.true_color.as_ref() {
+            "always" => ColorDepth::TrueColor,
+            "never" => ColorDepth::Ansi256,
+            "auto" if opt.output_format == "html" => ColorDepth::TrueColor,
+            "auto" => get_terminal_color_depth(
+                env::get_env_var("COLORTERM").as_deref(),
+                env::get_env_var("TERM").as_deref(),
+                get_terminfo_colors,
+            ),
+            _ => {
+                eprintln!(
+                    "Invalid value for --24-bit-color option: {} (valid values are \"always\", \"never\", and \"auto\")",
+                    opt.true_color
+                );
+                process::exit(1);
+            }
+        },
     };
 
     config::get_config(
         opt,
         assets.syntax_set,
         assets.theme_set,
-        true_color,
+        color_depth,
         paging_mode,
     )
 }
@@ -356,7 +736,7 @@ fn _check_validity(opt: &Opt, assets: &HighlightingAssets) {
                 eprintln!("Invalid theme: '{}'", theme);
                 process::exit(1);
             }
-            let is_light_theme = theme::is_light_theme(&theme);
+            let is_light_theme = theme::is_light_theme(theme, &assets.theme_set);
             if is_light_theme && opt.dark {
                 eprintln!(
                     "{} is a light theme, but you supplied --dark. \
@@ -385,8 +765,153 @@ pub fn unreachable(message: &str) -> ! {
     process::exit(1);
 }
 
-fn is_truecolor_terminal() -> bool {
-    env::get_env_var("COLORTERM")
-        .map(|colorterm| colorterm == "truecolor" || colorterm == "24bit")
-        .unwrap_or(false)
+/// Return the number of colors of the terminal, given the values of the COLORTERM and TERM
+/// environment variables. A terminal supports 24-bit color if COLORTERM says so. Otherwise, it is
+/// assumed to support 256 colors, as do almost all terminal emulators, even those whose terminfo
+/// entry (e.g. that of TERM=xterm) gives 8. Only a terminal such as the Linux console or a VT100,
+/// or one whose terminfo entry gives fewer than 8 colors, is limited to 16.
+fn get_terminal_color_depth(
+    colorterm: Option<&str>,
+    term: Option<&str>,
+    get_terminfo_colors: impl Fn() -> Option<i32>,
+) -> ColorDepth {
+    if let Some("truecolor") | Some("24bit") = colorterm {
+        return ColorDepth::TrueColor;
+    }
+    match term {
+        Some(term) if term.contains("256color") => ColorDepth::Ansi256,
+        Some(term) if term == "linux" || is_vt_terminal(term) => ColorDepth::Ansi16,
+        Some(_) => match get_terminfo_colors() {
+            Some(colors) if colors < 8 => ColorDepth::Ansi16,
+            _ => ColorDepth::Ansi256,
+        },
+        None => ColorDepth::Ansi256,
+    }
+}
+
+/// Is TERM that of a DEC VT terminal, e.g. "vt100" or "vt220"?
+fn is_vt_terminal(term: &str) -> bool {
+    term.starts_with("vt") && term[2..].starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Return the number of colors given by the terminfo entry for the terminal.
+fn get_terminfo_colors() -> Option<i32> {
+    process::Command::new("tput")
+        .arg("colors")
+        .stderr(process::Stdio::null())
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|colors| colors.trim().parse::<i32>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+
+    use crate::cli::{self, get_terminal_color_depth, Opt};
+    use crate::config::ColorDepth;
+    use crate::git_config::GitConfig;
+    use crate::rewrite;
+
+    fn get_options(args: &[&str], git_config: &str) -> Opt {
+        let arg_matches = Opt::clap().get_matches_from(args);
+        let mut opt = Opt::from_clap(&arg_matches);
+        cli::set_options(&mut opt, &arg_matches, Some(&GitConfig::parse(git_config)));
+        opt
+    }
+
+    #[test]
+    fn test_options_are_set_from_git_config() {
+        let opt = get_options(
+            &["delta"],
+            "delta.side-by-side\ntrue\0delta.minus-style\nred bold\0delta.tabs\n2\0\
+             delta.width\n80\0delta.true-color\nnever\0",
+        );
+        assert!(opt.side_by_side);
+        assert_eq!(opt.minus_style, "red bold");
+        assert_eq!(opt.tab_width, 2);
+        assert_eq!(opt.width, Some("80".to_string()));
+        assert_eq!(opt.true_color, "never");
+        assert_eq!(opt.plus_style, "syntax auto");
+    }
+
+    #[test]
+    fn test_command_line_options_take_precedence_over_git_config() {
+        let opt = get_options(
+            &["delta", "--minus-style", "green", "--tabs=8", "-w", "100"],
+            "delta.minus-style\nred bold\0delta.tabs\n2\0delta.width\n80\0",
+        );
+        assert_eq!(opt.minus_style, "green");
+        assert_eq!(opt.tab_width, 8);
+        assert_eq!(opt.width, Some("100".to_string()));
+    }
+
+    #[test]
+    fn test_deprecated_options_from_git_config_are_rewritten() {
+        let mut opt = get_options(&["delta"], "delta.hunk-style\nunderline\0");
+        rewrite::apply_rewrite_rules(&mut opt);
+        assert_eq!(opt.deprecated_hunk_style, None);
+        assert_eq!(opt.hunk_header_decoration_style, "underline");
+    }
+
+    #[test]
+    fn test_color_only_from_git_config_activates_color_only_feature() {
+        let opt = get_options(&["delta", "--file-style=red"], "delta.color-only\0");
+        assert_eq!(opt.features, Some("color-only".to_string()));
+        assert_eq!(opt.file_style, "red");
+        assert_eq!(opt.hunk_header_decoration_style, "none");
+    }
+
+    #[test]
+    fn test_features_from_command_line() {
+        let git_config = "delta.features\ndense\0\
+                          delta.dense.file-style\nblue\0\
+                          delta.presentation.side-by-side\0\
+                          delta.presentation.file-style\nred\0\
+                          delta.presentation.tabs\n8\0";
+        let opt = get_options(
+            &["delta", "--features=presentation", "--tabs=2"],
+            git_config,
+        );
+        assert!(opt.side_by_side);
+        assert_eq!(opt.file_style, "red");
+        assert_eq!(opt.tab_width, 2);
+
+        let opt = get_options(&["delta"], git_config);
+        assert!(!opt.side_by_side);
+        assert_eq!(opt.file_style, "blue");
+    }
+
+    #[test]
+    fn test_terminal_color_depth() {
+        let terminfo_colors = |colors| move || Some(colors);
+        for (colorterm, term, colors, expected) in vec![
+            (
+                Some("truecolor"),
+                Some("xterm-256color"),
+                256,
+                ColorDepth::TrueColor,
+            ),
+            (Some("24bit"), Some("xterm"), 8, ColorDepth::TrueColor),
+            (None, Some("xterm-256color"), 256, ColorDepth::Ansi256),
+            (None, Some("xterm"), 8, ColorDepth::Ansi256),
+            (None, Some("screen"), 8, ColorDepth::Ansi256),
+            (None, Some("linux"), 8, ColorDepth::Ansi16),
+            (None, Some("vt100"), -1, ColorDepth::Ansi16),
+            (None, Some("vt220"), 8, ColorDepth::Ansi16),
+            (None, Some("vte"), 8, ColorDepth::Ansi256),
+            (None, Some("ansi-mono"), 2, ColorDepth::Ansi16),
+            (None, None, -1, ColorDepth::Ansi256),
+        ] {
+            assert_eq!(
+                get_terminal_color_depth(colorterm, term, terminfo_colors(colors)),
+                expected
+            );
+        }
+        assert_eq!(
+            get_terminal_color_depth(None, Some("xterm"), || None),
+            ColorDepth::Ansi256
+        );
+    }
 }
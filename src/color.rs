@@ -1,10 +1,14 @@
+use std::cmp::max;
 use std::process;
 use std::str::FromStr;
 
 use ansi_term::Color;
 use syntect::highlighting::Color as SyntectColor;
+use syntect::highlighting::{Highlighter, Theme};
+use syntect::parsing::Scope;
 
 use crate::bat::terminal::to_ansi_color;
+use crate::config::ColorDepth;
 use crate::syntect_color;
 
 pub fn color_from_rgb_or_ansi_code(s: &str, true_color: bool) -> Color {
@@ -39,6 +43,23 @@ pub fn color_from_rgb_or_ansi_code_with_default(
     }
 }
 
+/// Return a string that, used as a color in a style string, specifies the color.
+pub fn color_to_string(color: Color) -> String {
+    match color {
+        Color::Fixed(n) if n < 16 => ansi_16_color_number_to_name(n).unwrap().to_string(),
+        Color::Fixed(n) => format!("{}", n),
+        Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Black => "black".to_string(),
+        Color::Red => "red".to_string(),
+        Color::Green => "green".to_string(),
+        Color::Yellow => "yellow".to_string(),
+        Color::Blue => "blue".to_string(),
+        Color::Purple => "magenta".to_string(),
+        Color::Cyan => "cyan".to_string(),
+        Color::White => "white".to_string(),
+    }
+}
+
 // See
 // https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit
 pub fn ansi_color_name_to_number(name: &str) -> Option<u8> {
@@ -74,42 +95,349 @@ pub fn ansi_color_name_to_number(name: &str) -> Option<u8> {
     }
 }
 
-pub fn get_minus_background_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_MINUS_COLOR,
-        (true, false) => LIGHT_THEME_MINUS_COLOR_256,
-        (false, true) => DARK_THEME_MINUS_COLOR,
-        (false, false) => DARK_THEME_MINUS_COLOR_256,
+fn ansi_16_color_number_to_name(n: u8) -> Option<&'static str> {
+    match n {
+        0 => Some("black"),
+        1 => Some("red"),
+        2 => Some("green"),
+        3 => Some("yellow"),
+        4 => Some("blue"),
+        5 => Some("magenta"),
+        6 => Some("cyan"),
+        7 => Some("white"),
+        8 => Some("brightblack"),
+        9 => Some("brightred"),
+        10 => Some("brightgreen"),
+        11 => Some("brightyellow"),
+        12 => Some("brightblue"),
+        13 => Some("brightmagenta"),
+        14 => Some("brightcyan"),
+        15 => Some("brightwhite"),
+        _ => None,
     }
 }
 
-pub fn get_minus_emph_background_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_MINUS_EMPH_COLOR,
-        (true, false) => LIGHT_THEME_MINUS_EMPH_COLOR_256,
-        (false, true) => DARK_THEME_MINUS_EMPH_COLOR,
-        (false, false) => DARK_THEME_MINUS_EMPH_COLOR_256,
+/// Return whether a color, given by red, green and blue components between 0 and 1, is light,
+/// i.e. whether its luminance is more than half the maximum.
+pub fn is_light_rgb(r: f64, g: f64, b: f64) -> bool {
+    0.2126 * r + 0.7152 * g + 0.0722 * b > 0.5
+}
+
+pub fn get_minus_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+    theme: Option<&Theme>,
+) -> Color {
+    if let Some((color, _, _, _)) =
+        get_theme_diff_background_colors(theme, is_light_mode, color_depth)
+    {
+        return color;
+    }
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_MINUS_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_MINUS_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_MINUS_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_MINUS_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_MINUS_COLOR_256,
     }
 }
 
-pub fn get_plus_background_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_PLUS_COLOR,
-        (true, false) => LIGHT_THEME_PLUS_COLOR_256,
-        (false, true) => DARK_THEME_PLUS_COLOR,
-        (false, false) => DARK_THEME_PLUS_COLOR_256,
+pub fn get_minus_emph_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+    theme: Option<&Theme>,
+) -> Color {
+    if let Some((_, color, _, _)) =
+        get_theme_diff_background_colors(theme, is_light_mode, color_depth)
+    {
+        return color;
+    }
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_MINUS_EMPH_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_MINUS_EMPH_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_MINUS_EMPH_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_MINUS_EMPH_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_MINUS_EMPH_COLOR_256,
     }
 }
 
-pub fn get_plus_emph_background_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
+pub fn get_plus_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+    theme: Option<&Theme>,
+) -> Color {
+    if let Some((_, _, color, _)) =
+        get_theme_diff_background_colors(theme, is_light_mode, color_depth)
+    {
+        return color;
+    }
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_PLUS_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_PLUS_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_PLUS_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_PLUS_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_PLUS_COLOR_256,
+    }
+}
+
+pub fn get_plus_emph_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+    theme: Option<&Theme>,
+) -> Color {
+    if let Some((_, _, _, color)) =
+        get_theme_diff_background_colors(theme, is_light_mode, color_depth)
+    {
+        return color;
+    }
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_PLUS_EMPH_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_PLUS_EMPH_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_PLUS_EMPH_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_PLUS_EMPH_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_PLUS_EMPH_COLOR_256,
+    }
+}
+
+/// Return the minus, minus-emph, plus and plus-emph background colors derived from the theme, if
+/// they can all be derived and are distinct from the default colors of moved lines. With 16
+/// colors, there is no room for tints, so the basic red and green are used instead.
+fn get_theme_diff_background_colors(
+    theme: Option<&Theme>,
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+) -> Option<(Color, Color, Color, Color)> {
+    let is_true_color = match color_depth {
+        ColorDepth::Ansi16 => return None,
+        ColorDepth::Ansi256 => false,
+        ColorDepth::TrueColor => true,
+    };
+    let (minus_color, minus_emph_color) =
+        get_theme_tinted_background_colors(theme, true, is_light_mode, is_true_color)?;
+    let (plus_color, plus_emph_color) =
+        get_theme_tinted_background_colors(theme, false, is_light_mode, is_true_color)?;
+    let colors = [minus_color, minus_emph_color, plus_color, plus_emph_color];
+    let moved_colors = [
+        get_moved_from_background_color_default(is_light_mode, color_depth),
+        get_moved_to_background_color_default(is_light_mode, color_depth),
+    ];
+    if colors.iter().any(|color| moved_colors.contains(color)) {
+        return None;
+    }
+    Some((minus_color, minus_emph_color, plus_color, plus_emph_color))
+}
+
+/// Return background colors for minus or plus lines, and for their emphasized sections, that
+/// harmonize with the theme: the theme's background blended with the color that the theme gives
+/// to deleted or inserted text in a diff (brightened to full intensity), or with red or green if it
+/// gives none. Return None if there is no theme, if its background is not an RGB color, if its
+/// background is not of the light or dark mode in effect, or if the 256-color approximations do
+/// not distinguish the colors from gray or from each other.
+fn get_theme_tinted_background_colors(
+    theme: Option<&Theme>,
+    is_minus: bool,
+    is_light_mode: bool,
+    is_true_color: bool,
+) -> Option<(Color, Color)> {
+    let theme = theme?;
+    let background = theme.settings.background.filter(|color| color.a > 0)?;
+    let to_fraction = |component: u8| component as f64 / 255.0;
+    if is_light_rgb(
+        to_fraction(background.r),
+        to_fraction(background.g),
+        to_fraction(background.b),
+    ) != is_light_mode
+    {
+        return None;
+    }
+    let (scope_names, default_tint) = if is_minus {
+        (["markup.deleted", "diff.deleted"], DIFF_MINUS_TINT)
+    } else {
+        (["markup.inserted", "diff.inserted"], DIFF_PLUS_TINT)
+    };
+    let highlighter = Highlighter::new(theme);
+    let tint = scope_names
+        .iter()
+        .filter_map(|scope_name| Scope::new(scope_name).ok())
+        .filter_map(|scope| highlighter.style_mod_for_stack(&[scope]).foreground)
+        .find(|color| color.a > 0 && max(color.r, max(color.g, color.b)) > 0)
+        .map(|color| {
+            let scale = 255.0 / max(color.r, max(color.g, color.b)) as f64;
+            let brighten = |component: u8| (component as f64 * scale).round() as u8;
+            SyntectColor {
+                r: brighten(color.r),
+                g: brighten(color.g),
+                b: brighten(color.b),
+                a: 0xff,
+            }
+        })
+        .unwrap_or(default_tint);
+    let (tint_proportion, emph_tint_proportion) = if is_light_mode {
+        (0.15, 0.3)
+    } else {
+        (0.2, 0.4)
+    };
+    let blend = |tint_proportion: f64| {
+        let blend_component = |background: u8, tint: u8| {
+            (background as f64 * (1.0 - tint_proportion) + tint as f64 * tint_proportion).round()
+                as u8
+        };
+        (
+            blend_component(background.r, tint.r),
+            blend_component(background.g, tint.g),
+            blend_component(background.b, tint.b),
+        )
+    };
+    let ((r, g, b), (emph_r, emph_g, emph_b)) =
+        (blend(tint_proportion), blend(emph_tint_proportion));
+    if is_true_color {
+        return Some((Color::RGB(r, g, b), Color::RGB(emph_r, emph_g, emph_b)));
+    }
+    let color = get_256_color_cube_approximation(r, g, b)?;
+    let emph_color = get_256_color_cube_approximation(emph_r, emph_g, emph_b)?;
+    if color == emph_color {
+        return None;
+    }
+    Some((Color::Fixed(color), Color::Fixed(emph_color)))
+}
+
+/// Return the nearest color of the 6x6x6 color cube of a 256-color terminal, or None if that is a
+/// gray. (The nearest of all 256 colors to a dark or pale tint is often one of the grays.)
+fn get_256_color_cube_approximation(r: u8, g: u8, b: u8) -> Option<u8> {
+    const LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+    let get_index = |component: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| (LEVELS[i] as i16 - component as i16).abs())
+            .unwrap() as u8
+    };
+    let (r, g, b) = (get_index(r), get_index(g), get_index(b));
+    if r == g && g == b {
+        None
+    } else {
+        Some(16 + 36 * r + 6 * g + b)
+    }
+}
+
+/// Return the number (0-15) of the basic terminal color that is nearest to a color, taking the
+/// basic colors to have their xterm default values.
+pub fn get_ansi_16_color_number(color: Color) -> u8 {
+    let (r, g, b) = match color {
+        Color::Black => return 0,
+        Color::Red => return 1,
+        Color::Green => return 2,
+        Color::Yellow => return 3,
+        Color::Blue => return 4,
+        Color::Purple => return 5,
+        Color::Cyan => return 6,
+        Color::White => return 7,
+        Color::Fixed(n) if n < 16 => return n,
+        Color::Fixed(n) => ansi_colours::rgb_from_ansi256(n),
+        Color::RGB(r, g, b) => (r, g, b),
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .iter()
+            .map(|&(c, c2)| (c as i32 - c2 as i32).pow(2))
+            .sum::<i32>()
+    };
+    (0..XTERM_16_COLORS.len())
+        .min_by_key(|&i| distance(XTERM_16_COLORS[i]))
+        .unwrap() as u8
+}
+
+const XTERM_16_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+pub fn get_moved_from_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+) -> Color {
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_MOVED_FROM_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_MOVED_FROM_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_MOVED_FROM_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_MOVED_FROM_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_MOVED_FROM_COLOR_256,
+    }
+}
+
+pub fn get_moved_to_background_color_default(
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+) -> Color {
+    match (is_light_mode, color_depth) {
+        (_, ColorDepth::Ansi16) => ANSI_16_MOVED_TO_COLOR,
+        (true, ColorDepth::TrueColor) => LIGHT_THEME_MOVED_TO_COLOR,
+        (true, ColorDepth::Ansi256) => LIGHT_THEME_MOVED_TO_COLOR_256,
+        (false, ColorDepth::TrueColor) => DARK_THEME_MOVED_TO_COLOR,
+        (false, ColorDepth::Ansi256) => DARK_THEME_MOVED_TO_COLOR_256,
+    }
+}
+
+pub fn get_line_numbers_minus_foreground_color_default() -> Color {
+    LINE_NUMBERS_MINUS_COLOR
+}
+
+pub fn get_line_numbers_zero_foreground_color_default(
+    is_light_mode: bool,
+    is_true_color: bool,
+) -> Color {
     match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_PLUS_EMPH_COLOR,
-        (true, false) => LIGHT_THEME_PLUS_EMPH_COLOR_256,
-        (false, true) => DARK_THEME_PLUS_EMPH_COLOR,
-        (false, false) => DARK_THEME_PLUS_EMPH_COLOR_256,
+        (true, true) => LIGHT_THEME_LINE_NUMBERS_ZERO_COLOR,
+        (true, false) => LIGHT_THEME_LINE_NUMBERS_ZERO_COLOR_256,
+        (false, true) => DARK_THEME_LINE_NUMBERS_ZERO_COLOR,
+        (false, false) => DARK_THEME_LINE_NUMBERS_ZERO_COLOR_256,
     }
 }
 
+pub fn get_line_numbers_plus_foreground_color_default() -> Color {
+    LINE_NUMBERS_PLUS_COLOR
+}
+
+const DIFF_MINUS_TINT: SyntectColor = SyntectColor {
+    r: 0xff,
+    g: 0x00,
+    b: 0x00,
+    a: 0xff,
+};
+
+const DIFF_PLUS_TINT: SyntectColor = SyntectColor {
+    r: 0x00,
+    g: 0xff,
+    b: 0x00,
+    a: 0xff,
+};
+
+// With 16 colors, the emphasized sections use the bright variants of the basic colors.
+
+const ANSI_16_MINUS_COLOR: Color = Color::Red;
+
+const ANSI_16_MINUS_EMPH_COLOR: Color = Color::Fixed(9);
+
+const ANSI_16_PLUS_COLOR: Color = Color::Green;
+
+const ANSI_16_PLUS_EMPH_COLOR: Color = Color::Fixed(10);
+
+const ANSI_16_MOVED_FROM_COLOR: Color = Color::Purple;
+
+const ANSI_16_MOVED_TO_COLOR: Color = Color::Blue;
+
 const LIGHT_THEME_MINUS_COLOR: Color = Color::RGB(0xff, 0xe0, 0xe0);
 
 const LIGHT_THEME_MINUS_COLOR_256: Color = Color::Fixed(224);
@@ -141,3 +469,142 @@ const DARK_THEME_PLUS_COLOR_256: Color = Color::Fixed(22);
 const DARK_THEME_PLUS_EMPH_COLOR: Color = Color::RGB(0x00, 0x60, 0x00);
 
 const DARK_THEME_PLUS_EMPH_COLOR_256: Color = Color::Fixed(28);
+
+const LIGHT_THEME_MOVED_FROM_COLOR: Color = Color::RGB(0xf0, 0xe0, 0xff);
+
+const LIGHT_THEME_MOVED_FROM_COLOR_256: Color = Color::Fixed(225);
+
+const LIGHT_THEME_MOVED_TO_COLOR: Color = Color::RGB(0xd8, 0xf0, 0xff);
+
+const LIGHT_THEME_MOVED_TO_COLOR_256: Color = Color::Fixed(195);
+
+const DARK_THEME_MOVED_FROM_COLOR: Color = Color::RGB(0x30, 0x10, 0x40);
+
+const DARK_THEME_MOVED_FROM_COLOR_256: Color = Color::Fixed(53);
+
+const DARK_THEME_MOVED_TO_COLOR: Color = Color::RGB(0x00, 0x28, 0x38);
+
+const DARK_THEME_MOVED_TO_COLOR_256: Color = Color::Fixed(23);
+
+const LINE_NUMBERS_MINUS_COLOR: Color = Color::Fixed(1);
+
+const LINE_NUMBERS_PLUS_COLOR: Color = Color::Fixed(2);
+
+const LIGHT_THEME_LINE_NUMBERS_ZERO_COLOR: Color = Color::RGB(0xdd, 0xdd, 0xdd);
+
+const LIGHT_THEME_LINE_NUMBERS_ZERO_COLOR_256: Color = Color::Fixed(253);
+
+const DARK_THEME_LINE_NUMBERS_ZERO_COLOR: Color = Color::RGB(0x44, 0x44, 0x44);
+
+const DARK_THEME_LINE_NUMBERS_ZERO_COLOR_256: Color = Color::Fixed(238);
+
+#[cfg(test)]
+mod tests {
+    use ansi_term::Color;
+    use syntect::highlighting::{Color as SyntectColor, Theme};
+
+    use super::{
+        get_256_color_cube_approximation, get_ansi_16_color_number,
+        get_minus_background_color_default, get_minus_emph_background_color_default,
+        get_plus_background_color_default, DARK_THEME_MINUS_COLOR,
+    };
+    use crate::bat::assets::HighlightingAssets;
+    use crate::config::ColorDepth;
+
+    fn make_theme(background: (u8, u8, u8)) -> Theme {
+        let mut theme = Theme::default();
+        theme.settings.background = Some(SyntectColor {
+            r: background.0,
+            g: background.1,
+            b: background.2,
+            a: 0xff,
+        });
+        theme
+    }
+
+    #[test]
+    fn test_background_colors_are_tints_of_theme_background() {
+        let theme = make_theme((0x20, 0x20, 0x40));
+        assert_eq!(
+            get_minus_background_color_default(false, ColorDepth::TrueColor, Some(&theme)),
+            Color::RGB(0x4d, 0x1a, 0x33)
+        );
+        assert_eq!(
+            get_minus_emph_background_color_default(false, ColorDepth::TrueColor, Some(&theme)),
+            Color::RGB(0x79, 0x13, 0x26)
+        );
+        assert_eq!(
+            get_plus_background_color_default(false, ColorDepth::TrueColor, Some(&theme)),
+            Color::RGB(0x1a, 0x4d, 0x33)
+        );
+    }
+
+    #[test]
+    fn test_background_colors_use_theme_diff_colors() {
+        let theme_set = HighlightingAssets::from_binary().theme_set;
+        let monokai = &theme_set.themes["Monokai Extended"];
+        // Monokai's deleted text is pink rather than red.
+        assert_eq!(
+            get_minus_background_color_default(false, ColorDepth::TrueColor, Some(monokai)),
+            Color::RGB(0x4e, 0x23, 0x33)
+        );
+    }
+
+    #[test]
+    fn test_background_colors_have_256_color_approximations() {
+        let theme = make_theme((0xf0, 0xf0, 0xe0));
+        assert_eq!(
+            get_minus_background_color_default(true, ColorDepth::Ansi256, Some(&theme)),
+            Color::Fixed(223)
+        );
+        assert_eq!(
+            get_plus_background_color_default(true, ColorDepth::Ansi256, Some(&theme)),
+            Color::Fixed(193)
+        );
+    }
+
+    #[test]
+    fn test_256_color_cube_approximation() {
+        assert_eq!(get_256_color_cube_approximation(0x59, 0x26, 0x26), Some(52));
+        assert_eq!(
+            get_256_color_cube_approximation(0xfd, 0xda, 0xc9),
+            Some(224)
+        );
+        // The nearest color of the cube to a dark tint may be a gray.
+        assert_eq!(get_256_color_cube_approximation(0x2a, 0x20, 0x20), None);
+    }
+
+    #[test]
+    fn test_background_colors_fall_back_to_defaults() {
+        assert_eq!(
+            get_minus_background_color_default(false, ColorDepth::TrueColor, None),
+            DARK_THEME_MINUS_COLOR
+        );
+        // A light theme in dark mode.
+        let theme = make_theme((0xff, 0xff, 0xff));
+        assert_eq!(
+            get_minus_background_color_default(false, ColorDepth::TrueColor, Some(&theme)),
+            DARK_THEME_MINUS_COLOR
+        );
+    }
+
+    #[test]
+    fn test_ansi_16_color_number() {
+        assert_eq!(get_ansi_16_color_number(Color::Purple), 5);
+        assert_eq!(get_ansi_16_color_number(Color::Fixed(9)), 9);
+        assert_eq!(get_ansi_16_color_number(Color::Fixed(196)), 9);
+        assert_eq!(get_ansi_16_color_number(Color::RGB(0x3f, 0x00, 0x01)), 0);
+        assert_eq!(get_ansi_16_color_number(Color::RGB(0xd0, 0x10, 0x10)), 1);
+        assert_eq!(get_ansi_16_color_number(Color::RGB(0xf8, 0xf8, 0xf2)), 15);
+    }
+
+    #[test]
+    fn test_ansi_16_background_colors_keep_emph_tiers_distinct() {
+        let theme = make_theme((0x27, 0x28, 0x22));
+        let minus = get_minus_background_color_default(false, ColorDepth::Ansi16, Some(&theme));
+        let minus_emph =
+            get_minus_emph_background_color_default(false, ColorDepth::Ansi16, Some(&theme));
+        assert_eq!(get_ansi_16_color_number(minus), 1);
+        assert_eq!(get_ansi_16_color_number(minus_emph), 9);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use git_delta::events::{DiffEvent, DiffEventParser};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::delta::expand_tabs;
+
+/// A block of moved lines is only displayed as moved if it contains at least this many
+/// alphanumeric characters. This is the same threshold as git uses, and prevents short, common
+/// lines (e.g. a closing brace) from being displayed as moved.
+const MIN_ALNUM_COUNT: usize = 20;
+
+/// The runs of removed and added lines in a diff. This is used to recognise blocks of removed
+/// lines that are added elsewhere in the diff, and vice versa. As with git's default
+/// `--color-moved` mode, lines are compared exactly, so a block that has been re-indented is not
+/// displayed as moved.
+pub struct MovedLines {
+    minus_runs: Runs,
+    plus_runs: Runs,
+}
+
+impl MovedLines {
+    /// Record the removed and added lines of every hunk in the diff.
+    pub fn from_diff(input: &str, tab_width: usize) -> Self {
+        let mut minus_runs = Runs::default();
+        let mut plus_runs = Runs::default();
+        let mut minus_run = Vec::new();
+        let mut plus_run = Vec::new();
+        for event in DiffEventParser::new(input.as_bytes()).map_while(Result::ok) {
+            if !matches!(event, DiffEvent::MinusLine { .. }) {
+                minus_runs.push(&mut minus_run);
+            }
+            if !matches!(event, DiffEvent::PlusLine { .. }) {
+                plus_runs.push(&mut plus_run);
+            }
+            match event {
+                DiffEvent::MinusLine { content, .. } => {
+                    minus_run.push(expand_tabs(content.graphemes(true), tab_width))
+                }
+                DiffEvent::PlusLine { content, .. } => {
+                    plus_run.push(expand_tabs(content.graphemes(true), tab_width))
+                }
+                _ => {}
+            }
+        }
+        minus_runs.push(&mut minus_run);
+        plus_runs.push(&mut plus_run);
+        Self {
+            minus_runs,
+            plus_runs,
+        }
+    }
+
+    /// For each of a consecutive run of removed lines, as prepared for painting, return whether
+    /// it belongs to a block that has been added elsewhere in the diff.
+    pub fn get_moved_from_lines(&self, minus_lines: &[String]) -> Vec<bool> {
+        get_moved_lines(minus_lines, &self.plus_runs)
+    }
+
+    /// For each of a consecutive run of added lines, as prepared for painting, return whether it
+    /// belongs to a block that has been removed elsewhere in the diff.
+    pub fn get_moved_to_lines(&self, plus_lines: &[String]) -> Vec<bool> {
+        get_moved_lines(plus_lines, &self.minus_runs)
+    }
+}
+
+/// Runs of consecutive removed (or added) lines, indexed by line content.
+#[derive(Default)]
+struct Runs {
+    runs: Vec<Vec<String>>,
+    positions: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl Runs {
+    /// Record a run of lines, leaving `run` empty.
+    fn push(&mut self, run: &mut Vec<String>) {
+        if run.is_empty() {
+            return;
+        }
+        for (i, line) in run.iter().enumerate() {
+            self.positions
+                .entry(line.clone())
+                .or_default()
+                .push((self.runs.len(), i));
+        }
+        self.runs.push(std::mem::take(run));
+    }
+
+    /// Return the length of the longest block of `lines`, starting at the first, that occurs as
+    /// a contiguous block in one of the runs.
+    fn get_longest_match(&self, lines: &[&str]) -> usize {
+        let positions = match lines.first().and_then(|line| self.positions.get(*line)) {
+            Some(positions) => positions,
+            None => return 0,
+        };
+        positions
+            .iter()
+            .map(|(run, start)| {
+                self.runs[*run][*start..]
+                    .iter()
+                    .zip(lines.iter())
+                    .take_while(|(run_line, line)| run_line == *line)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A line is moved if it is part of a block of lines that occurs, in the same order, in a run of
+/// lines on the other side of the diff, and the block contains enough alphanumeric characters in
+/// total. Blocks are matched greedily, longest first.
+fn get_moved_lines(lines: &[String], other_side_runs: &Runs) -> Vec<bool> {
+    // Remove the prefix column and line terminator added by delta::prepare.
+    let keys: Vec<&str> = lines
+        .iter()
+        .map(|line| {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect();
+    let mut moved = vec![false; lines.len()];
+    let mut i = 0;
+    while i < keys.len() {
+        let block_length = other_side_runs.get_longest_match(&keys[i..]);
+        if block_length == 0 {
+            i += 1;
+            continue;
+        }
+        let alnum_count: usize = keys[i..i + block_length]
+            .iter()
+            .map(|key| key.chars().filter(|c| c.is_alphanumeric()).count())
+            .sum();
+        if alnum_count >= MIN_ALNUM_COUNT {
+            for is_moved in &mut moved[i..i + block_length] {
+                *is_moved = true;
+            }
+        }
+        i += block_length;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_get_moved_lines() {
+        let mut other_side_runs = Runs::default();
+        other_side_runs.push(
+            &mut vec![
+                "fn print_square(num: f64) {",
+                "    let result = f64::powf(num, 2.0);",
+                "}",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        );
+        let lines: Vec<String> = vec![
+            " fn print_square(num: f64) {\n",
+            "     let result = f64::powf(num, 2.0);\n",
+            " }\n",
+            " \n",
+            " }\n",
+            "         let result = f64::powf(num, 2.0);\n",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        // A lone closing brace is too short to be moved, and a re-indented line does not match.
+        assert_eq!(
+            get_moved_lines(&lines, &other_side_runs),
+            vec![true, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_color_moved_across_files() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.color_moved = true;
+        options.moved_to_style = "normal auto".to_string();
+        let (output, config) = integration_test_utils::run_delta(MOVED_BLOCK_DIFF, options);
+        let moved_from_prefix = config.moved_from_style.ansi_term_style.prefix().to_string();
+        let moved_to_prefix = config.moved_to_style.ansi_term_style.prefix().to_string();
+        let minus_prefix = config.minus_style.ansi_term_style.prefix().to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        let find_line = |text: &str| {
+            *lines
+                .iter()
+                .find(|line| strip_ansi_codes(line).trim() == text)
+                .unwrap()
+        };
+        // The moved block is removed from the first file and added to the second.
+        assert!(find_line("fn print_square(num: f64) {").starts_with(&moved_from_prefix));
+        assert!(find_line("let result = f64::powf(num, 2.0);").starts_with(&moved_from_prefix));
+        assert!(lines
+            .iter()
+            .filter(|line| strip_ansi_codes(line).trim() == "fn print_square(num: f64) {")
+            .any(|line| line.starts_with(&moved_to_prefix)));
+        // A removed line that is not added elsewhere keeps the minus style.
+        assert!(find_line("println!(\"unrelated\");").starts_with(&minus_prefix));
+    }
+
+    #[test]
+    fn test_color_moved_off_by_default() {
+        let options = integration_test_utils::get_command_line_options();
+        let (output, config) = integration_test_utils::run_delta(MOVED_BLOCK_DIFF, options);
+        let moved_from_prefix = config.moved_from_style.ansi_term_style.prefix().to_string();
+        assert!(!output.contains(&moved_from_prefix));
+    }
+
+    #[test]
+    fn test_color_moved_does_not_ignore_reindentation() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.color_moved = true;
+        let (output, config) = integration_test_utils::run_delta(REINDENTED_BLOCK_DIFF, options);
+        let moved_from_prefix = config.moved_from_style.ansi_term_style.prefix().to_string();
+        let moved_to_prefix = config.moved_to_style.ansi_term_style.prefix().to_string();
+        assert!(!output.contains(&moved_from_prefix));
+        assert!(!output.contains(&moved_to_prefix));
+    }
+
+    const MOVED_BLOCK_DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+index f38589a..0f1bb83 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,6 +1,1 @@
+-fn print_square(num: f64) {
+-    let result = f64::powf(num, 2.0);
+-    println!(\"Square({}) = {}\", num, result);
+-}
+-    println!(\"unrelated\");
+ fn main() {}
+diff --git a/src/b.rs b/src/b.rs
+index f38589a..0f1bb83 100644
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -1,1 +1,5 @@
++fn print_square(num: f64) {
++    let result = f64::powf(num, 2.0);
++    println!(\"Square({}) = {}\", num, result);
++}
+ mod shapes {}
+";
+
+    const REINDENTED_BLOCK_DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+index f38589a..0f1bb83 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,4 +1,6 @@
+-fn print_square(num: f64) {
+-    let result = f64::powf(num, 2.0);
+-}
++mod shapes {
++    fn print_square(num: f64) {
++        let result = f64::powf(num, 2.0);
++    }
++}
+ fn main() {}
+";
+}
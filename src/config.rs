@@ -1,7 +1,9 @@
 use std::cmp::min;
+use std::path::PathBuf;
 use std::process;
 
 use console::Term;
+use git_delta::parse::State;
 use syntect::highlighting::Style as SyntectStyle;
 use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
@@ -9,9 +11,11 @@ use syntect::parsing::SyntaxSet;
 use crate::bat::output::PagingMode;
 use crate::cli::{self, unreachable};
 use crate::color;
-use crate::delta::State;
 use crate::env;
+use crate::git;
 use crate::style::Style;
+use crate::syntax_mapping::SyntaxMapping;
+use crate::terminal_background;
 use crate::theme;
 
 pub enum Width {
@@ -19,9 +23,26 @@ pub enum Width {
     Variable,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum OutputFormat {
+    Ansi,
+    Html,
+    Json,
+    Plain,
+}
+
+/// The number of colors that the terminal can display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
 pub struct Config<'a> {
     pub theme: Option<Theme>,
     pub theme_name: String,
+    pub is_light_mode: bool,
     pub dummy_theme: Theme,
     pub max_line_distance: f64,
     pub max_line_distance_for_naively_paired_lines: f64,
@@ -32,20 +53,46 @@ pub struct Config<'a> {
     pub plus_style: Style,
     pub plus_emph_style: Style,
     pub plus_non_emph_style: Style,
+    pub whitespace_error_style: Style,
+    pub color_moved: bool,
+    pub full_file_highlighting: bool,
+    pub moved_from_style: Style,
+    pub moved_to_style: Style,
     pub minus_line_marker: &'a str,
     pub plus_line_marker: &'a str,
+    pub keep_plus_minus_markers: bool,
     pub commit_style: Style,
     pub file_style: Style,
     pub hunk_header_style: Style,
+    pub line_numbers: bool,
+    pub line_numbers_minus_style: Style,
+    pub line_numbers_zero_style: Style,
+    pub line_numbers_plus_style: Style,
+    pub line_numbers_left_style: Style,
+    pub line_numbers_right_style: Style,
+    pub line_numbers_left_format: String,
+    pub line_numbers_right_format: String,
     pub syntax_set: SyntaxSet,
+    pub syntax_mapping: SyntaxMapping,
     pub decorations_width: Width,
+    pub available_terminal_width: usize,
+    pub side_by_side: bool,
+    pub hyperlinks: bool,
+    pub hyperlinks_file_link_format: String,
+    pub hyperlinks_commit_link_format: Option<String>,
+    pub git_root: Option<PathBuf>,
     pub true_color: bool,
+    pub color_depth: ColorDepth,
+    pub monochrome: bool,
     pub background_color_extends_to_terminal_width: bool,
     pub tab_width: usize,
     pub null_style: Style,
     pub null_syntect_style: SyntectStyle,
     pub max_buffered_lines: usize,
     pub paging_mode: PagingMode,
+    pub output_format: OutputFormat,
+    pub json_syntax_scopes: bool,
+    pub features: Vec<String>,
 }
 
 impl<'a> Config<'a> {
@@ -63,9 +110,10 @@ pub fn get_config<'a>(
     opt: cli::Opt,
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
-    true_color: bool,
+    color_depth: ColorDepth,
     paging_mode: PagingMode,
 ) -> Config<'a> {
+    let true_color = color_depth == ColorDepth::TrueColor;
     // Allow one character for e.g. `less --status-column` is in effect. See #41 and #10.
     let available_terminal_width = (Term::stdout().size().1 - 1) as usize;
     let (decorations_width, background_color_extends_to_terminal_width) = match opt.width.as_deref()
@@ -81,14 +129,69 @@ pub fn get_config<'a>(
         None => (Width::Fixed(available_terminal_width), true),
     };
 
+    let output_format = match opt.output_format.as_ref() {
+        "ansi" => OutputFormat::Ansi,
+        "html" => OutputFormat::Html,
+        "json" => OutputFormat::Json,
+        "plain" => OutputFormat::Plain,
+        _ => {
+            eprintln!(
+                "Invalid value for --output-format option: {} (valid values are \"ansi\", \"html\", \"json\", and \"plain\")",
+                opt.output_format
+            );
+            process::exit(1);
+        }
+    };
+
+    let monochrome = match opt.color.as_ref() {
+        "auto" => env::get_env_var("NO_COLOR").is_some(),
+        "always" => false,
+        "never" => true,
+        _ => {
+            eprintln!(
+                "Invalid value for --color option: {} (valid values are \"auto\", \"always\", and \"never\")",
+                opt.color
+            );
+            process::exit(1);
+        }
+    } && output_format == OutputFormat::Ansi;
+
     let theme_name_from_bat_pager = env::get_env_var("BAT_THEME");
+    // The terminal background is detected only if nothing else determines the mode.
+    let light_mode_arg = if opt.light
+        || opt.dark
+        || opt.theme.is_some()
+        || theme_name_from_bat_pager.is_some()
+    {
+        opt.light
+    } else {
+        let should_detect = match opt.detect_dark_light.as_ref() {
+            "auto" => atty::is(atty::Stream::Stdout),
+            "always" => true,
+            "never" => false,
+            _ => {
+                eprintln!(
+                    "Invalid value for --detect-dark-light option: {} (valid values are \"auto\", \"always\", and \"never\")",
+                    opt.detect_dark_light
+                );
+                process::exit(1);
+            }
+        };
+        should_detect && terminal_background::detect_is_light_background().unwrap_or(false)
+    };
     let (is_light_mode, theme_name) = theme::get_is_light_mode_and_theme_name(
         opt.theme.as_ref(),
         theme_name_from_bat_pager.as_ref(),
-        opt.light,
+        light_mode_arg,
         &theme_set,
     );
 
+    let theme = if theme::is_no_syntax_highlighting_theme_name(&theme_name) {
+        None
+    } else {
+        Some(theme_set.themes[&theme_name].clone())
+    };
+
     let (
         minus_style,
         minus_emph_style,
@@ -97,24 +200,46 @@ pub fn get_config<'a>(
         plus_style,
         plus_emph_style,
         plus_non_emph_style,
-    ) = make_hunk_styles(&opt, is_light_mode, true_color);
+    ) = make_hunk_styles(&opt, is_light_mode, color_depth, theme.as_ref());
 
-    let (commit_style, file_style, hunk_header_style) =
-        make_commit_file_hunk_header_styles(&opt, true_color);
+    let (moved_from_style, moved_to_style) = make_moved_styles(&opt, is_light_mode, color_depth);
 
-    let theme = if theme::is_no_syntax_highlighting_theme_name(&theme_name) {
-        None
+    let (minus_emph_style, plus_emph_style, moved_from_style, moved_to_style) = if monochrome {
+        (
+            add_attribute_if_colors_only(minus_emph_style, ansi_term::Style::reverse),
+            add_attribute_if_colors_only(plus_emph_style, ansi_term::Style::reverse),
+            add_attribute_if_colors_only(moved_from_style, ansi_term::Style::underline),
+            add_attribute_if_colors_only(moved_to_style, ansi_term::Style::underline),
+        )
     } else {
-        Some(theme_set.themes[&theme_name].clone())
+        (
+            minus_emph_style,
+            plus_emph_style,
+            moved_from_style,
+            moved_to_style,
+        )
     };
+
+    let (commit_style, file_style, hunk_header_style) =
+        make_commit_file_hunk_header_styles(&opt, true_color);
+
+    let (
+        line_numbers_minus_style,
+        line_numbers_zero_style,
+        line_numbers_plus_style,
+        line_numbers_left_style,
+        line_numbers_right_style,
+    ) = make_line_numbers_styles(&opt, is_light_mode, true_color);
+
     let dummy_theme = theme_set.themes.values().next().unwrap().clone();
 
-    let minus_line_marker = if opt.keep_plus_minus_markers {
+    // Without colors, the markers are what distinguishes removed from added lines.
+    let minus_line_marker = if opt.keep_plus_minus_markers || monochrome {
         "-"
     } else {
         " "
     };
-    let plus_line_marker = if opt.keep_plus_minus_markers {
+    let plus_line_marker = if opt.keep_plus_minus_markers || monochrome {
         "+"
     } else {
         " "
@@ -128,6 +253,7 @@ pub fn get_config<'a>(
     Config {
         theme,
         theme_name,
+        is_light_mode,
         dummy_theme,
         max_line_distance: opt.max_line_distance,
         max_line_distance_for_naively_paired_lines,
@@ -138,34 +264,103 @@ pub fn get_config<'a>(
         plus_style,
         plus_emph_style,
         plus_non_emph_style,
+        whitespace_error_style: Style::from_str(
+            &opt.whitespace_error_style,
+            None,
+            None,
+            None,
+            true_color,
+            false,
+        ),
+        color_moved: opt.color_moved,
+        full_file_highlighting: opt.full_file_highlighting,
+        moved_from_style,
+        moved_to_style,
         minus_line_marker,
         plus_line_marker,
+        keep_plus_minus_markers: opt.keep_plus_minus_markers,
         commit_style,
         file_style,
         hunk_header_style,
+        line_numbers: opt.line_numbers,
+        line_numbers_minus_style,
+        line_numbers_zero_style,
+        line_numbers_plus_style,
+        line_numbers_left_style,
+        line_numbers_right_style,
+        line_numbers_left_format: opt.line_numbers_left_format,
+        line_numbers_right_format: opt.line_numbers_right_format,
         true_color,
+        color_depth,
+        monochrome,
         decorations_width,
+        available_terminal_width,
+        side_by_side: opt.side_by_side,
+        hyperlinks: opt.hyperlinks,
+        hyperlinks_file_link_format: opt.hyperlinks_file_link_format,
+        hyperlinks_commit_link_format: opt.hyperlinks_commit_link_format,
+        git_root: if opt.hyperlinks || opt.full_file_highlighting {
+            git::get_git_root()
+        } else {
+            None
+        },
         background_color_extends_to_terminal_width,
         tab_width: opt.tab_width,
+        syntax_mapping: SyntaxMapping::from_strs(&opt.map_syntax, &syntax_set),
         syntax_set,
         null_style: Style::new(),
         null_syntect_style: SyntectStyle::default(),
         max_buffered_lines: 32,
         paging_mode,
+        output_format,
+        json_syntax_scopes: opt.json_syntax_scopes,
+        features: opt
+            .features
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+    }
+}
+
+/// Return the style with an attribute added if it is distinguished only by its colors, so that it
+/// remains distinguished when colors are not used.
+fn add_attribute_if_colors_only(
+    style: Style,
+    add_attribute: fn(&ansi_term::Style) -> ansi_term::Style,
+) -> Style {
+    let has_colors =
+        style.ansi_term_style.foreground.is_some() || style.ansi_term_style.background.is_some();
+    let attributes = ansi_term::Style {
+        foreground: None,
+        background: None,
+        ..style.ansi_term_style
+    };
+    if has_colors && attributes == ansi_term::Style::new() {
+        Style {
+            ansi_term_style: add_attribute(&style.ansi_term_style),
+            ..style
+        }
+    } else {
+        style
     }
 }
 
 fn make_hunk_styles<'a>(
     opt: &'a cli::Opt,
     is_light_mode: bool,
-    true_color: bool,
+    color_depth: ColorDepth,
+    theme: Option<&Theme>,
 ) -> (Style, Style, Style, Style, Style, Style, Style) {
+    let true_color = color_depth == ColorDepth::TrueColor;
     let minus_style = Style::from_str(
         &opt.minus_style,
         None,
         Some(color::get_minus_background_color_default(
             is_light_mode,
-            true_color,
+            color_depth,
+            theme,
         )),
         None,
         true_color,
@@ -177,7 +372,8 @@ fn make_hunk_styles<'a>(
         None,
         Some(color::get_minus_emph_background_color_default(
             is_light_mode,
-            true_color,
+            color_depth,
+            theme,
         )),
         None,
         true_color,
@@ -204,7 +400,8 @@ fn make_hunk_styles<'a>(
         None,
         Some(color::get_plus_background_color_default(
             is_light_mode,
-            true_color,
+            color_depth,
+            theme,
         )),
         None,
         true_color,
@@ -216,7 +413,8 @@ fn make_hunk_styles<'a>(
         None,
         Some(color::get_plus_emph_background_color_default(
             is_light_mode,
-            true_color,
+            color_depth,
+            theme,
         )),
         None,
         true_color,
@@ -247,6 +445,94 @@ fn make_hunk_styles<'a>(
     )
 }
 
+fn make_moved_styles(
+    opt: &cli::Opt,
+    is_light_mode: bool,
+    color_depth: ColorDepth,
+) -> (Style, Style) {
+    let true_color = color_depth == ColorDepth::TrueColor;
+    let moved_from_style = Style::from_str(
+        &opt.moved_from_style,
+        None,
+        Some(color::get_moved_from_background_color_default(
+            is_light_mode,
+            color_depth,
+        )),
+        None,
+        true_color,
+        false,
+    );
+    let moved_to_style = Style::from_str(
+        &opt.moved_to_style,
+        None,
+        Some(color::get_moved_to_background_color_default(
+            is_light_mode,
+            color_depth,
+        )),
+        None,
+        true_color,
+        false,
+    );
+    (moved_from_style, moved_to_style)
+}
+
+fn make_line_numbers_styles(
+    opt: &cli::Opt,
+    is_light_mode: bool,
+    true_color: bool,
+) -> (Style, Style, Style, Style, Style) {
+    let line_numbers_minus_style = Style::from_str(
+        &opt.line_numbers_minus_style,
+        Some(color::get_line_numbers_minus_foreground_color_default()),
+        None,
+        None,
+        true_color,
+        false,
+    );
+    let line_numbers_zero_style = Style::from_str(
+        &opt.line_numbers_zero_style,
+        Some(color::get_line_numbers_zero_foreground_color_default(
+            is_light_mode,
+            true_color,
+        )),
+        None,
+        None,
+        true_color,
+        false,
+    );
+    let line_numbers_plus_style = Style::from_str(
+        &opt.line_numbers_plus_style,
+        Some(color::get_line_numbers_plus_foreground_color_default()),
+        None,
+        None,
+        true_color,
+        false,
+    );
+    let line_numbers_left_style = Style::from_str(
+        &opt.line_numbers_left_style,
+        None,
+        None,
+        None,
+        true_color,
+        false,
+    );
+    let line_numbers_right_style = Style::from_str(
+        &opt.line_numbers_right_style,
+        None,
+        None,
+        None,
+        true_color,
+        false,
+    );
+    (
+        line_numbers_minus_style,
+        line_numbers_zero_style,
+        line_numbers_plus_style,
+        line_numbers_left_style,
+        line_numbers_right_style,
+    )
+}
+
 fn make_commit_file_hunk_header_styles(opt: &cli::Opt, true_color: bool) -> (Style, Style, Style) {
     (
         Style::from_str_with_handling_of_special_decoration_attributes_and_respecting_deprecated_foreground_color_arg(
@@ -356,7 +642,6 @@ mod tests {
             } else {
                 env::set_var("BAT_THEME", bat_theme_env_var);
             }
-            let is_true_color = true;
             let mut options = integration_test_utils::get_command_line_options();
             options.theme = theme_option;
             match mode_option {
@@ -378,34 +663,41 @@ mod tests {
             if theme::is_no_syntax_highlighting_theme_name(expected_theme) {
                 assert!(config.theme.is_none())
             } else {
-                assert_eq!(config.theme.unwrap().name.as_ref().unwrap(), expected_theme);
+                assert_eq!(
+                    config.theme.as_ref().unwrap().name.as_ref().unwrap(),
+                    expected_theme
+                );
             }
             assert_eq!(
                 config.minus_style.ansi_term_style.background.unwrap(),
                 color::get_minus_background_color_default(
                     expected_mode == Mode::Light,
-                    is_true_color
+                    config.color_depth,
+                    config.theme.as_ref()
                 )
             );
             assert_eq!(
                 config.minus_emph_style.ansi_term_style.background.unwrap(),
                 color::get_minus_emph_background_color_default(
                     expected_mode == Mode::Light,
-                    is_true_color
+                    config.color_depth,
+                    config.theme.as_ref()
                 )
             );
             assert_eq!(
                 config.plus_style.ansi_term_style.background.unwrap(),
                 color::get_plus_background_color_default(
                     expected_mode == Mode::Light,
-                    is_true_color
+                    config.color_depth,
+                    config.theme.as_ref()
                 )
             );
             assert_eq!(
                 config.plus_emph_style.ansi_term_style.background.unwrap(),
                 color::get_plus_emph_background_color_default(
                     expected_mode == Mode::Light,
-                    is_true_color
+                    config.color_depth,
+                    config.theme.as_ref()
                 )
             );
         }
@@ -1,42 +1,23 @@
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::io::BufRead;
 use std::io::Write;
 
 use bytelines::ByteLines;
-use console::strip_ansi_codes;
+use git_delta::events::{DiffEvent, DiffEventParser};
+use git_delta::parse::{self, Source, State};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use crate::config::Config;
-use crate::draw;
+use crate::color_moved::MovedLines;
+use crate::config::{Config, Width};
+use crate::hyperlinks;
 use crate::paint::Painter;
-use crate::parse;
-use crate::style::DecorationStyle;
-
-#[derive(Clone, Debug, PartialEq)]
-pub enum State {
-    CommitMeta, // In commit metadata section
-    FileMeta,   // In diff metadata section, between (possible) commit metadata and first hunk
-    HunkHeader, // In hunk metadata line
-    HunkZero,   // In hunk; unchanged line
-    HunkMinus,  // In hunk; removed line
-    HunkPlus,   // In hunk; added line
-    Unknown,
-}
-
-#[derive(Debug, PartialEq)]
-pub enum Source {
-    GitDiff,     // Coming from a `git diff` command
-    DiffUnified, // Coming from a `diff -u` command
-    Unknown,
-}
-
-impl State {
-    fn is_in_hunk(&self) -> bool {
-        match *self {
-            State::HunkHeader | State::HunkZero | State::HunkMinus | State::HunkPlus => true,
-            _ => false,
-        }
-    }
-}
+use crate::render;
+use crate::side_by_side;
+use crate::style::{DecorationStyle, Style};
+use crate::whitespace_errors;
+use crate::word_diff;
 
 // Possible transitions, with actions on entry:
 //
@@ -59,135 +40,288 @@ where
     I: BufRead,
 {
     let mut painter = Painter::new(writer, config);
+    if config.color_moved {
+        // A moved block may be added before it is removed, so the whole diff must be read before
+        // any line can be painted.
+        let mut input = String::new();
+        while let Some(Ok(raw_line_bytes)) = lines.next() {
+            input.push_str(&String::from_utf8_lossy(raw_line_bytes));
+            input.push('\n');
+        }
+        painter.moved_lines = Some(MovedLines::from_diff(&input, config.tab_width));
+        _delta(ByteLines::new(input.as_bytes()), painter, config)
+    } else {
+        _delta(lines, painter, config)
+    }
+}
+
+fn _delta<I>(lines: ByteLines<I>, mut painter: Painter, config: &Config) -> std::io::Result<()>
+where
+    I: BufRead,
+{
+    let mut parser = DiffEventParser::from_byte_lines(lines);
     let mut minus_file = "".to_string();
-    let mut plus_file;
+    let mut plus_file = "".to_string();
     let mut state = State::Unknown;
     let mut source = Source::Unknown;
+    let mut diff_stat_lines = Vec::new();
+    // Whether the syntax of the current file is to be detected from the first line of the file,
+    // since its path did not determine it.
+    let mut is_syntax_from_first_line_pending = false;
+    // The blob hashes from the index line of the current file.
+    let mut blob_hashes: Option<(String, String)> = None;
 
-    while let Some(Ok(raw_line_bytes)) = lines.next() {
-        let raw_line = String::from_utf8_lossy(&raw_line_bytes);
-        let line = strip_ansi_codes(&raw_line).to_string();
+    while let Some(Ok((event, raw_line))) = parser.next_with_raw_line() {
         if source == Source::Unknown {
-            source = detect_source(&line);
+            if let DiffEvent::CommitHeader { line, .. } | DiffEvent::FileMeta { line } = &event {
+                source = parse::detect_source(line);
+            }
         }
-        if line.starts_with("commit ") {
-            painter.paint_buffered_lines();
-            state = State::CommitMeta;
-            if should_handle(&state, config) {
+        let is_diff_stat_line = match &event {
+            DiffEvent::CommitMeta { line } | DiffEvent::Other { line } => {
+                parse::parse_diff_stat_line(line).is_some()
+            }
+            _ => false,
+        };
+        if !diff_stat_lines.is_empty() && !is_diff_stat_line {
+            painter.emit()?;
+            handle_diff_stat_lines(&mut painter, &diff_stat_lines, config)?;
+            diff_stat_lines.clear();
+        }
+        match event {
+            DiffEvent::CommitHeader { line, .. } => {
+                painter.paint_buffered_lines();
+                state = State::CommitMeta;
                 painter.emit()?;
-                handle_commit_meta_header_line(&mut painter, &line, &raw_line, config)?;
-                continue;
+                if should_handle(&state, config)
+                    || config.hyperlinks && config.hyperlinks_commit_link_format.is_some()
+                {
+                    handle_commit_meta_header_line(&mut painter, &line, &raw_line, config)?;
+                } else {
+                    write_painted_line(painter.writer, &raw_line, config)?;
+                }
             }
-        } else if line.starts_with("diff ") {
-            painter.paint_buffered_lines();
-            state = State::FileMeta;
-        } else if (state == State::FileMeta || source == Source::DiffUnified)
-            && (line.starts_with("--- ") || line.starts_with("rename from "))
-            && should_handle(&State::FileMeta, config)
-        {
-            minus_file = parse::get_file_path_from_file_meta_line(&line, source == Source::GitDiff);
-            if source == Source::DiffUnified {
-                state = State::FileMeta;
-                painter.set_syntax(parse::get_file_extension_from_marker_line(&line));
-            } else {
-                state = State::FileMeta;
-                painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
-                    &minus_file,
-                ));
+            DiffEvent::FileMeta { line } => {
+                if state != State::FileMeta {
+                    // The metadata of a new file.
+                    painter.paint_buffered_lines();
+                    state = State::FileMeta;
+                    is_syntax_from_first_line_pending = false;
+                    blob_hashes = None;
+                }
+                if line.starts_with("index ") {
+                    blob_hashes = parse::get_blob_hashes_from_index_line(&line).map(
+                        |(minus_hash, plus_hash)| (minus_hash.to_string(), plus_hash.to_string()),
+                    );
+                }
+                if is_additional_file_meta_line(&line, &source) {
+                    handle_additional_file_meta_line(&mut painter, &line, &raw_line, config)?;
+                } else if !should_handle(&State::FileMeta, config) {
+                    painter.emit()?;
+                    write_painted_line(painter.writer, &raw_line, config)?;
+                }
+                // Otherwise, the file metadata lines are replaced by the file header.
             }
-        } else if (line.starts_with("+++ ") || line.starts_with("rename to "))
-            && should_handle(&State::FileMeta, config)
-        {
-            plus_file = parse::get_file_path_from_file_meta_line(&line, source == Source::GitDiff);
-            painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
-                &plus_file,
-            ));
-            painter.emit()?;
-            handle_file_meta_header_line(
-                &mut painter,
-                &minus_file,
-                &plus_file,
-                config,
-                source == Source::DiffUnified,
-            )?;
-        } else if line.starts_with("@@") {
-            state = State::HunkHeader;
-            painter.set_highlighter();
-            if should_handle(&state, config) {
+            DiffEvent::FileHeader {
+                minus_file: file_header_minus_file,
+                plus_file: file_header_plus_file,
+            } => {
+                minus_file = file_header_minus_file;
+                plus_file = file_header_plus_file;
+                is_syntax_from_first_line_pending = painter.set_syntaxes(&minus_file, &plus_file);
+                if config.full_file_highlighting
+                    && (blob_hashes.is_some() || source == Source::DiffUnified)
+                {
+                    painter.set_file_contents(
+                        &minus_file,
+                        &plus_file,
+                        blob_hashes.as_ref().map(|(minus_hash, plus_hash)| {
+                            (minus_hash.as_str(), plus_hash.as_str())
+                        }),
+                    );
+                } else {
+                    painter.clear_file_contents();
+                }
+                if should_handle(&State::FileMeta, config) {
+                    painter.emit()?;
+                    handle_file_meta_header_line(
+                        &mut painter,
+                        &minus_file,
+                        &plus_file,
+                        config,
+                        source == Source::DiffUnified,
+                    )?;
+                }
+            }
+            DiffEvent::HunkHeader {
+                line, line_numbers, ..
+            } => {
+                // A shebang or modeline is only found on the first line of the file.
+                is_syntax_from_first_line_pending = is_syntax_from_first_line_pending
+                    && line_numbers.iter().any(|(start, _)| *start == 1);
+                painter.paint_buffered_lines();
+                state = State::HunkHeader;
+                painter.prime_highlighters(&line_numbers);
                 painter.emit()?;
-                handle_hunk_header_line(&mut painter, &line, &raw_line, config)?;
-                continue;
+                if should_handle(&state, config) {
+                    handle_hunk_header_line(
+                        &mut painter,
+                        &line,
+                        &raw_line,
+                        &minus_file,
+                        &plus_file,
+                        config,
+                    )?;
+                } else {
+                    write_painted_line(painter.writer, &raw_line, config)?;
+                }
             }
-        } else if source == Source::DiffUnified && line.starts_with("Only in ")
-            || line.starts_with("Submodule ")
-            || line.starts_with("Binary files ")
-        {
-            // Additional FileMeta cases:
-            //
-            // 1. When comparing directories with diff -u, if filenames match between the
-            //    directories, the files themselves will be compared. However, if an equivalent
-            //    filename is not present, diff outputs a single line (Only in...) starting
-            //    indicating that the file is present in only one of the directories.
-            //
-            // 2. Git diff emits lines describing submodule state such as "Submodule x/y/z contains
-            //    untracked content"
-            //
-            // See https://github.com/dandavison/delta/issues/60#issuecomment-557485242 for a
-            // proposal for more robust parsing logic.
-
-            state = State::FileMeta;
-            painter.paint_buffered_lines();
-            if should_handle(&State::FileMeta, config) {
+            DiffEvent::MinusLine { prefix, content }
+            | DiffEvent::PlusLine { prefix, content }
+            | DiffEvent::ZeroLine { prefix, content } => {
+                if is_syntax_from_first_line_pending {
+                    is_syntax_from_first_line_pending = false;
+                    painter.set_syntax_from_first_line(&content);
+                }
+                state = handle_hunk_line(&mut painter, &prefix, &content, state, config);
                 painter.emit()?;
-                handle_generic_file_meta_header_line(&mut painter, &line, &raw_line, config)?;
-                continue;
             }
-        } else if state.is_in_hunk() {
-            // A true hunk line should start with one of: '+', '-', ' '. However, handle_hunk_line
-            // handles all lines until the state machine transitions away from the hunk states.
-            state = handle_hunk_line(&mut painter, &line, &raw_line, state, config);
-            painter.emit()?;
-            continue;
-        }
-
-        if state == State::FileMeta && should_handle(&State::FileMeta, config) {
-            // The file metadata section is 4 lines. Skip them under non-plain file-styles.
-            continue;
-        } else {
-            painter.emit()?;
-            writeln!(painter.writer, "{}", raw_line)?;
+            DiffEvent::WordDiffLine { tokens } => {
+                if is_syntax_from_first_line_pending {
+                    is_syntax_from_first_line_pending = false;
+                    let line: String = tokens.iter().map(|(_, text)| text.as_str()).collect();
+                    painter.set_syntax_from_first_line(&line);
+                }
+                state = handle_word_diff_line(&mut painter, &tokens, state, config);
+                painter.emit()?;
+            }
+            DiffEvent::CommitMeta { line } | DiffEvent::Other { line } => {
+                if is_additional_file_meta_line(&line, &source) {
+                    state = State::FileMeta;
+                    painter.paint_buffered_lines();
+                    handle_additional_file_meta_line(&mut painter, &line, &raw_line, config)?;
+                } else if (state == State::CommitMeta
+                    || state == State::DiffStat
+                    || state == State::Unknown)
+                    && is_diff_stat_line
+                {
+                    state = State::DiffStat;
+                    if should_handle(&State::FileMeta, config) {
+                        // The diffstat lines are painted together, so that they can be aligned.
+                        diff_stat_lines.push(line);
+                    } else {
+                        painter.emit()?;
+                        write_painted_line(painter.writer, &raw_line, config)?;
+                    }
+                } else if state == State::DiffStat
+                    && parse::is_diff_stat_summary_line(&line)
+                    && should_handle(&State::FileMeta, config)
+                {
+                    painter.emit()?;
+                    handle_diff_stat_summary_line(&mut painter, &line, config)?;
+                } else if state.is_in_hunk() {
+                    // E.g. "\ No newline at end of file", which is not a hunk line, but is
+                    // displayed among them.
+                    state = handle_non_hunk_line_in_hunk(&mut painter, &raw_line, config);
+                    painter.emit()?;
+                } else {
+                    painter.emit()?;
+                    write_painted_line(painter.writer, &raw_line, config)?;
+                }
+            }
         }
     }
 
     painter.paint_buffered_lines();
     painter.emit()?;
+    handle_diff_stat_lines(&mut painter, &diff_stat_lines, config)?;
     Ok(())
 }
 
-/// Should a handle_* function be called on this element?
-fn should_handle(state: &State, config: &Config) -> bool {
-    let style = config.get_style(state);
-    !(style.is_raw && style.decoration_style == DecorationStyle::NoDecoration)
+/// Additional FileMeta cases:
+///
+/// 1. When comparing directories with diff -u, if filenames match between the directories, the
+///    files themselves will be compared. However, if an equivalent filename is not present, diff
+///    outputs a single line (Only in...) starting indicating that the file is present in only one
+///    of the directories.
+///
+/// 2. Git diff emits lines describing submodule state such as "Submodule x/y/z contains untracked
+///    content"
+///
+/// 3. A binary file is described by a "Binary files ... differ" line instead of hunks.
+///
+/// See https://github.com/dandavison/delta/issues/60#issuecomment-557485242 for a proposal for more
+/// robust parsing logic.
+fn is_additional_file_meta_line(line: &str, source: &Source) -> bool {
+    *source == Source::DiffUnified && line.starts_with("Only in ")
+        || line.starts_with("Submodule ")
+        || line.starts_with("Binary files ")
 }
 
-/// Try to detect what is producing the input for delta.
-///
-/// Currently can detect:
-/// * git diff
-/// * diff -u
-fn detect_source(line: &str) -> Source {
-    if line.starts_with("commit ") || line.starts_with("diff --git ") {
-        Source::GitDiff
-    } else if line.starts_with("diff -u ")
-        || line.starts_with("diff -U")
-        || line.starts_with("--- ")
-    {
-        Source::DiffUnified
+fn handle_additional_file_meta_line(
+    painter: &mut Painter,
+    line: &str,
+    raw_line: &str,
+    config: &Config,
+) -> std::io::Result<()> {
+    painter.emit()?;
+    if should_handle(&State::FileMeta, config) {
+        handle_generic_file_meta_header_line(painter, line, raw_line, config)
     } else {
-        Source::Unknown
+        write_painted_line(painter.writer, raw_line, config)
     }
 }
 
+/// Write a line painted with ANSI escape sequences, e.g. a raw line of input.
+fn write_painted_line(writer: &mut dyn Write, line: &str, config: &Config) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{}",
+        render::get_renderer(config).paint_ansi_text(line)
+    )
+}
+
+/// Write a commit, file, or hunk header line, painted and decorated according to `style`. If
+/// `pad_box` is true, a space is added after text that is drawn in a box.
+fn write_decorated_header_line(
+    painter: &mut Painter,
+    line: &str,
+    raw_line: &str,
+    style: Style,
+    pad_box: bool,
+    config: &Config,
+) -> std::io::Result<()> {
+    let renderer = render::get_renderer(config);
+    let pad = match style.decoration_style {
+        DecorationStyle::Box(_)
+        | DecorationStyle::BoxWithUnderline(_)
+        | DecorationStyle::BoxWithOverline(_)
+        | DecorationStyle::BoxWithUnderOverline(_)
+            if pad_box =>
+        {
+            " "
+        }
+        _ => "",
+    };
+    let text = if style.is_raw {
+        renderer.paint_ansi_text(&format!("{}{}", raw_line, pad))
+    } else {
+        renderer.paint(style.ansi_term_style, &format!("{}{}", line, pad))
+    };
+    renderer.write_decorated(
+        painter.writer,
+        &text,
+        &config.decorations_width,
+        style.decoration_style,
+    )
+}
+
+/// Should a handle_* function be called on this element?
+fn should_handle(state: &State, config: &Config) -> bool {
+    let style = config.get_style(state);
+    !(style.is_raw && style.decoration_style == DecorationStyle::NoDecoration)
+}
+
 fn handle_commit_meta_header_line(
     painter: &mut Painter,
     line: &str,
@@ -197,55 +331,15 @@ fn handle_commit_meta_header_line(
     if config.commit_style.is_omitted {
         return Ok(());
     }
-    let decoration_ansi_term_style;
-    let mut pad = false;
-    let draw_fn = match config.commit_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
-        }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
-        }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
-        }
+    let (line, raw_line) = if config.hyperlinks {
+        (
+            hyperlinks::format_commit_line_with_osc8_commit_hyperlink(line, config),
+            hyperlinks::format_commit_line_with_osc8_commit_hyperlink(raw_line, config),
+        )
+    } else {
+        (Cow::from(line), Cow::from(raw_line))
     };
-    draw_fn(
-        painter.writer,
-        &format!("{}{}", line, if pad { " " } else { "" }),
-        &format!("{}{}", raw_line, if pad { " " } else { "" }),
-        &config.decorations_width,
-        config.commit_style,
-        decoration_ansi_term_style,
-    )?;
-    Ok(())
+    write_decorated_header_line(painter, &line, &raw_line, config.commit_style, true, config)
 }
 
 /// Construct file change line from minus and plus file and write with FileMeta styling.
@@ -256,7 +350,16 @@ fn handle_file_meta_header_line(
     config: &Config,
     comparing: bool,
 ) -> std::io::Result<()> {
-    let line = parse::get_file_change_description_from_file_paths(minus_file, plus_file, comparing);
+    let mut line =
+        parse::get_file_change_description_from_file_paths(minus_file, plus_file, comparing);
+    if config.hyperlinks && plus_file != "/dev/null" {
+        if let Some(i) = line.rfind(plus_file) {
+            line.replace_range(
+                i..i + plus_file.len(),
+                &hyperlinks::format_osc8_file_hyperlink(plus_file, None, plus_file, config),
+            );
+        }
+    }
     // FIXME: no support for 'raw'
     handle_generic_file_meta_header_line(painter, &line, &line, config)
 }
@@ -271,115 +374,175 @@ fn handle_generic_file_meta_header_line(
     if config.file_style.is_omitted {
         return Ok(());
     }
-    let decoration_ansi_term_style;
-    let mut pad = false;
-    let draw_fn = match config.file_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
+    writeln!(painter.writer)?;
+    write_decorated_header_line(painter, line, raw_line, config.file_style, true, config)
+}
+
+/// Write diffstat lines, with the file paths styled like file paths in file headers and the bars
+/// painted in the minus and plus styles. If a line does not fit within the decorations width, the
+/// path column is narrowed, and paths that do not fit in it are wrapped onto multiple lines, with
+/// the bar following the final segment.
+fn handle_diff_stat_lines(
+    painter: &mut Painter,
+    lines: &[String],
+    config: &Config,
+) -> std::io::Result<()> {
+    let diff_stat_lines = lines
+        .iter()
+        .filter_map(|line| parse::parse_diff_stat_line(line))
+        .collect::<Vec<_>>();
+    let get_stats_width = |diff_stat_line: &parse::DiffStatLine| {
+        UnicodeWidthStr::width(diff_stat_line.count)
+            + if diff_stat_line.bar.is_empty() {
+                0
+            } else {
+                1 + diff_stat_line.bar.len()
+            }
+    };
+    let git_path_column_width = diff_stat_lines
+        .iter()
+        .map(|diff_stat_line| diff_stat_line.path_column_width)
+        .max()
+        .unwrap_or(0);
+    let max_stats_width = diff_stat_lines
+        .iter()
+        .map(get_stats_width)
+        .max()
+        .unwrap_or(0);
+    // A line is " {path} | {count} {bar}".
+    let path_column_width = match config.decorations_width {
+        Width::Fixed(width) => min(
+            git_path_column_width,
+            max(width.saturating_sub(max_stats_width + 4), 1),
+        ),
+        Width::Variable => git_path_column_width,
+    };
+    let file_style = config.file_style.ansi_term_style;
+    for diff_stat_line in diff_stat_lines {
+        let mut path_segments = wrap_file_path(diff_stat_line.path, path_column_width);
+        let last_path_segment = path_segments.pop().unwrap_or_default();
+        for path_segment in path_segments {
+            write_painted_line(
+                painter.writer,
+                &format!(" {}", file_style.paint(path_segment)),
+                config,
+            )?;
         }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
+        let padding = " ".repeat(
+            path_column_width.saturating_sub(UnicodeWidthStr::width(last_path_segment.as_str())),
+        );
+        let mut line = format!(
+            " {}{} | {}",
+            file_style.paint(last_path_segment),
+            padding,
+            diff_stat_line.count
+        );
+        if !diff_stat_line.bar.is_empty() {
+            let bar = diff_stat_line
+                .bar
+                .chars()
+                .map(|c| match c {
+                    '+' => config.plus_style.ansi_term_style.paint(c.to_string()),
+                    _ => config.minus_style.ansi_term_style.paint(c.to_string()),
+                })
+                .collect::<Vec<_>>();
+            line.push_str(&format!(" {}", ansi_term::ANSIStrings(&bar)));
+        }
+        write_painted_line(painter.writer, &line, config)?;
+    }
+    Ok(())
+}
+
+/// Write the summary line of a diffstat, with the counts of insertions and deletions painted in the
+/// plus and minus styles.
+fn handle_diff_stat_summary_line(
+    painter: &mut Painter,
+    line: &str,
+    config: &Config,
+) -> std::io::Result<()> {
+    let sections = line
+        .split(", ")
+        .map(|section| {
+            if section.ends_with("(+)") {
+                config.plus_style.ansi_term_style.paint(section)
+            } else if section.ends_with("(-)") {
+                config.minus_style.ansi_term_style.paint(section)
+            } else {
+                config.null_style.ansi_term_style.paint(section)
+            }
+        })
+        .collect::<Vec<_>>();
+    let line = sections
+        .iter()
+        .map(|section| section.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    write_painted_line(painter.writer, &line, config)
+}
+
+/// Split a file path into segments whose display width is at most `width`, breaking after a path
+/// separator where possible.
+fn wrap_file_path(path: &str, width: usize) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut segment_width = 0;
+    let mut last_separator_end = None;
+    for grapheme in path.graphemes(true) {
+        let grapheme_width = UnicodeWidthStr::width(grapheme);
+        if segment_width + grapheme_width > width && !segment.is_empty() {
+            let remainder = match last_separator_end {
+                Some(i)
+                    if i < segment.len()
+                        && UnicodeWidthStr::width(&segment[i..]) + grapheme_width <= width =>
+                {
+                    segment.split_off(i)
+                }
+                _ => String::new(),
+            };
+            segments.push(segment);
+            segment_width = UnicodeWidthStr::width(remainder.as_str());
+            segment = remainder;
+            last_separator_end = None;
         }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
+        segment.push_str(grapheme);
+        segment_width += grapheme_width;
+        if grapheme == "/" {
+            last_separator_end = Some(segment.len());
         }
-    };
-    writeln!(painter.writer)?;
-    draw_fn(
-        painter.writer,
-        &format!("{}{}", line, if pad { " " } else { "" }),
-        &format!("{}{}", raw_line, if pad { " " } else { "" }),
-        &config.decorations_width,
-        config.file_style,
-        decoration_ansi_term_style,
-    )?;
-    Ok(())
+    }
+    if !segment.is_empty() || segments.is_empty() {
+        segments.push(segment);
+    }
+    segments
 }
 
 fn handle_hunk_header_line(
     painter: &mut Painter,
     line: &str,
     raw_line: &str,
+    minus_file: &str,
+    plus_file: &str,
     config: &Config,
 ) -> std::io::Result<()> {
     if config.hunk_header_style.is_omitted {
         return Ok(());
     }
-    let decoration_ansi_term_style;
-    let draw_fn = match config.hunk_header_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
-        }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
-        }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
-        }
-    };
-    let (raw_code_fragment, line_number) = parse::parse_hunk_metadata(&line);
+    let (raw_code_fragment, line_numbers) = parse::parse_hunk_metadata(line);
+    if let Some(line_numbers_data) = &mut painter.line_numbers_data {
+        line_numbers_data.initialize_hunk(&line_numbers);
+    }
     if config.hunk_header_style.is_raw {
         writeln!(painter.writer)?;
-        draw_fn(
-            painter.writer,
+        write_decorated_header_line(
+            painter,
             &format!("{} ", line),
             &format!("{} ", raw_line),
-            &config.decorations_width,
             config.hunk_header_style,
-            decoration_ansi_term_style,
+            false,
+            config,
         )?;
     } else {
-        let line = match prepare(raw_code_fragment, false, config) {
+        let line = match prepare(raw_code_fragment, 1, false, config) {
             s if s.len() > 0 => format!("{} ", s),
             s => s,
         };
@@ -389,37 +552,70 @@ fn handle_hunk_header_line(
             let syntax_style_sections = Painter::get_syntax_style_sections_for_lines(
                 &lines,
                 &State::HunkHeader,
-                &mut painter.highlighter,
+                &mut painter.get_out_of_context_highlighter(),
                 &painter.config,
             );
             Painter::paint_lines(
                 syntax_style_sections,
                 vec![vec![(config.hunk_header_style, &lines[0])]],
+                &State::HunkHeader,
                 &mut painter.output_buffer,
                 config,
-                "",
+                &mut None,
+                &["".to_string()],
                 config.null_style,
                 config.null_style,
                 Some(false),
             );
             painter.output_buffer.pop(); // trim newline
-            draw_fn(
+            render::get_renderer(config).write_decorated(
                 painter.writer,
                 &painter.output_buffer,
-                &painter.output_buffer,
                 &config.decorations_width,
-                config.hunk_header_style,
-                decoration_ansi_term_style,
+                config.hunk_header_style.decoration_style,
             )?;
             if !config.hunk_header_style.is_raw {
                 painter.output_buffer.clear()
             };
         }
     };
-    match config.hunk_header_style.decoration_ansi_term_style() {
-        Some(style) => writeln!(painter.writer, "{}", style.paint(line_number))?,
-        None => writeln!(painter.writer, "{}", line_number)?,
-    };
+    // The line numbers are displayed in the gutter when --line-numbers is in effect, so there is no
+    // need to display the starting line number of the plus file under the hunk header.
+    if !config.line_numbers {
+        let mut plus_line_number = line_numbers
+            .last()
+            .map(|(start, _)| start.to_string())
+            .unwrap_or_default();
+        if config.hyperlinks {
+            // A deleted file is linked at its location in the minus file.
+            let link_target = if plus_file == "/dev/null" {
+                line_numbers.first().map(|(start, _)| (minus_file, start))
+            } else {
+                line_numbers.last().map(|(start, _)| (plus_file, start))
+            };
+            if let Some((file, start)) = link_target {
+                plus_line_number = hyperlinks::format_osc8_file_hyperlink(
+                    file,
+                    Some(*start),
+                    &plus_line_number,
+                    config,
+                );
+            }
+        }
+        let renderer = render::get_renderer(config);
+        match config.hunk_header_style.decoration_ansi_term_style() {
+            Some(style) => writeln!(
+                painter.writer,
+                "{}",
+                renderer.paint(style, &plus_line_number)
+            )?,
+            None => writeln!(
+                painter.writer,
+                "{}",
+                renderer.paint_ansi_text(&plus_line_number)
+            )?,
+        };
+    }
     Ok(())
 }
 
@@ -429,13 +625,18 @@ fn handle_hunk_header_line(
 // minus and plus lines jointly, in order to paint detailed
 // highlighting according to inferred edit operations. In the case of
 // an unchanged line, we paint it immediately.
+//
+// A hunk line starts with one prefix column per parent: a single column in an ordinary diff, and
+// N columns in a combined diff of a merge commit with N parents. These are given by `prefix`.
 fn handle_hunk_line(
     painter: &mut Painter,
-    line: &str,
-    raw_line: &str,
+    prefix: &str,
+    content: &str,
     state: State,
     config: &Config,
 ) -> State {
+    let n_parents = prefix.len();
+    let line = &format!("{}{}", prefix, content);
     // Don't let the line buffers become arbitrarily large -- if we
     // were to allow that, then for a large deleted/added file we
     // would process the entire file before painting anything.
@@ -444,71 +645,182 @@ fn handle_hunk_line(
     {
         painter.paint_buffered_lines();
     }
-    match line.chars().next() {
-        Some('-') => {
+    match parse::get_hunk_line_state(prefix, n_parents) {
+        Some(State::HunkMinus) => {
             if state == State::HunkPlus {
                 painter.paint_buffered_lines();
             }
-            painter.minus_lines.push(prepare(&line, true, config));
+            painter.minus_line_prefixes.push(get_line_prefix(
+                prefix,
+                config.minus_line_marker,
+                config,
+            ));
+            painter
+                .minus_lines
+                .push(prepare(line, n_parents, true, config));
             State::HunkMinus
         }
-        Some('+') => {
-            painter.plus_lines.push(prepare(&line, true, config));
+        Some(State::HunkPlus) => {
+            painter.plus_line_prefixes.push(get_line_prefix(
+                prefix,
+                config.plus_line_marker,
+                config,
+            ));
+            painter.plus_line_whitespace_errors.push(
+                whitespace_errors::get_whitespace_error_ranges(content, config.tab_width),
+            );
+            painter
+                .plus_lines
+                .push(prepare(line, n_parents, true, config));
             State::HunkPlus
         }
-        Some(' ') => {
+        _ => {
             let state = State::HunkZero;
-            let prefix = if line.is_empty() { "" } else { &line[..1] };
             painter.paint_buffered_lines();
-            let lines = vec![prepare(&line, true, config)];
+            let lines = vec![prepare(line, n_parents, true, config)];
             let syntax_style_sections = Painter::get_syntax_style_sections_for_lines(
                 &lines,
                 &state,
-                &mut painter.highlighter,
+                &mut painter.plus_highlighter,
                 &painter.config,
             );
+            painter.update_minus_highlighter_with_zero_lines(&lines);
             let diff_style_sections = vec![(config.zero_style, lines[0].as_str())];
 
-            Painter::paint_lines(
-                syntax_style_sections,
-                vec![diff_style_sections],
-                &mut painter.output_buffer,
-                config,
-                prefix,
-                config.zero_style,
-                config.zero_style,
-                None,
-            );
+            if config.side_by_side {
+                side_by_side::paint_zero_lines_side_by_side(
+                    syntax_style_sections,
+                    vec![diff_style_sections],
+                    &mut painter.output_buffer,
+                    config,
+                    &mut painter.line_numbers_data,
+                    prefix,
+                );
+            } else {
+                Painter::paint_lines(
+                    syntax_style_sections,
+                    vec![diff_style_sections],
+                    &state,
+                    &mut painter.output_buffer,
+                    config,
+                    &mut painter.line_numbers_data,
+                    &[prefix.to_string()],
+                    config.zero_style,
+                    config.zero_style,
+                    None,
+                );
+            }
             state
         }
-        _ => {
-            // The first character here could be e.g. '\' from '\ No newline at end of file'. This
-            // is not a hunk line, but the parser does not have a more accurate state corresponding
-            // to this.
-            painter.paint_buffered_lines();
-            painter
-                .output_buffer
-                .push_str(&expand_tabs(raw_line.graphemes(true), config.tab_width));
-            painter.output_buffer.push_str("\n");
-            State::HunkZero
-        }
     }
 }
 
-/// Replace initial -/+ character with ' ', expand tabs as spaces, and optionally terminate with
-/// newline.
+/// Handle a line that is displayed among the hunk lines, but is not itself a hunk line, e.g. "\ No
+/// newline at end of file". It is displayed as it is, after any buffered lines.
+fn handle_non_hunk_line_in_hunk(painter: &mut Painter, raw_line: &str, config: &Config) -> State {
+    painter.paint_buffered_lines();
+    let line = expand_tabs(raw_line.graphemes(true), config.tab_width);
+    painter
+        .output_buffer
+        .push_str(&render::get_renderer(config).paint_ansi_text(&line));
+    painter.output_buffer.push('\n');
+    State::HunkZero
+}
+
+/// Handle a line of the original file in a word diff. An unchanged line is painted as a zero line.
+/// Otherwise, its old and new versions are buffered as minus and plus lines, together with the
+/// emph ranges given by the word-diff markers.
+fn handle_word_diff_line(
+    painter: &mut Painter,
+    word_diff_line: &[(char, String)],
+    state: State,
+    config: &Config,
+) -> State {
+    if word_diff::is_unchanged(word_diff_line) {
+        let line: String = word_diff_line
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect();
+        return handle_hunk_line(painter, " ", &line, state, config);
+    }
+    if painter.minus_lines.len() > config.max_buffered_lines
+        || painter.plus_lines.len() > config.max_buffered_lines
+    {
+        painter.paint_buffered_lines();
+    }
+    let word_diff_data = painter
+        .word_diff_data
+        .get_or_insert_with(word_diff::WordDiffData::default);
+    let mut minus_index = None;
+    let mut plus_index = None;
+    if let Some((line, emph_ranges)) = word_diff::get_line_and_emph_ranges(word_diff_line, '-') {
+        painter
+            .minus_line_prefixes
+            .push(config.minus_line_marker.to_string());
+        painter
+            .minus_lines
+            .push(prepare(&format!(" {}", line), 1, true, config));
+        word_diff_data
+            .minus_line_emph_ranges
+            .push(get_prepared_ranges(&line, &emph_ranges, config));
+        minus_index = Some(painter.minus_lines.len() - 1);
+    }
+    if let Some((line, emph_ranges)) = word_diff::get_line_and_emph_ranges(word_diff_line, '+') {
+        painter
+            .plus_line_prefixes
+            .push(config.plus_line_marker.to_string());
+        painter
+            .plus_line_whitespace_errors
+            .push(whitespace_errors::get_whitespace_error_ranges(
+                &line,
+                config.tab_width,
+            ));
+        painter
+            .plus_lines
+            .push(prepare(&format!(" {}", line), 1, true, config));
+        word_diff_data
+            .plus_line_emph_ranges
+            .push(get_prepared_ranges(&line, &emph_ranges, config));
+        plus_index = Some(painter.plus_lines.len() - 1);
+    }
+    word_diff_data
+        .line_alignment
+        .push((minus_index, plus_index));
+    match plus_index {
+        Some(_) => State::HunkPlus,
+        None => State::HunkMinus,
+    }
+}
+
+/// Return the prefix to display in place of the prefix columns of a removed or added line. In an
+/// ordinary diff this is the configured -/+ marker. In a combined diff it is the prefix columns
+/// themselves if markers are being kept, and blank columns otherwise.
+fn get_line_prefix(raw_prefix: &str, marker: &str, config: &Config) -> String {
+    if raw_prefix.len() == 1 {
+        marker.to_string()
+    } else if config.keep_plus_minus_markers {
+        raw_prefix.to_string()
+    } else {
+        " ".repeat(raw_prefix.len())
+    }
+}
+
+/// Replace the initial prefix columns (a single -/+/space character, except in a combined diff)
+/// with ' ', expand tabs as spaces, and optionally terminate with newline.
 // Terminating with newline character is necessary for many of the sublime syntax definitions to
 // highlight correctly.
 // See https://docs.rs/syntect/3.2.0/syntect/parsing/struct.SyntaxSetBuilder.html#method.add_from_folder
-fn prepare(line: &str, append_newline: bool, config: &Config) -> String {
+fn prepare(line: &str, prefix_width: usize, append_newline: bool, config: &Config) -> String {
     let terminator = if append_newline { "\n" } else { "" };
     if !line.is_empty() {
         let mut line = line.graphemes(true);
 
         // The first column contains a -/+/space character, added by git. We substitute it for a
         // space now, so that it is not present during syntax highlighting, and substitute again
-        // when emitting the line.
-        line.next();
+        // when emitting the line. In a combined diff there is one such column per parent.
+        for _ in 0..prefix_width {
+            line.next();
+        }
 
         format!(" {}{}", expand_tabs(line, config.tab_width), terminator)
     } else {
@@ -516,9 +828,25 @@ fn prepare(line: &str, append_newline: bool, config: &Config) -> String {
     }
 }
 
+fn get_prepared_ranges(
+    content: &str,
+    ranges: &[(usize, usize)],
+    config: &Config,
+) -> Vec<(usize, usize)> {
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            (
+                whitespace_errors::get_prepared_offset(content, *start, config.tab_width),
+                whitespace_errors::get_prepared_offset(content, *end, config.tab_width),
+            )
+        })
+        .collect()
+}
+
 /// Expand tabs as spaces.
 /// tab_width = 0 is documented to mean do not replace tabs.
-fn expand_tabs<'a, I>(line: I, tab_width: usize) -> String
+pub(crate) fn expand_tabs<'a, I>(line: I, tab_width: usize) -> String
 where
     I: Iterator<Item = &'a str>,
 {
@@ -7,32 +7,55 @@ use console::strip_ansi_codes;
 use unicode_width::UnicodeWidthStr;
 
 use crate::config::Width;
-use crate::style::Style;
+use crate::hyperlinks;
+use crate::render::Renderer;
+use crate::style::DecorationStyle;
 
-pub fn write_no_decoration(
+/// Write text (already painted by the renderer), decorated as specified by `decoration_style`.
+pub fn write_decorated<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
-    _line_width: &Width, // ignored
-    text_style: Style,
-    _decoration_style: ansi_term::Style,
+    line_width: &Width,
+    decoration_style: DecorationStyle,
 ) -> std::io::Result<()> {
-    if text_style.is_raw {
-        writeln!(writer, "{}", raw_text)?;
-    } else {
-        writeln!(writer, "{}", text_style.ansi_term_style.paint(text))?;
+    match decoration_style {
+        DecorationStyle::Box(style) => write_boxed(writer, renderer, text, line_width, style),
+        DecorationStyle::BoxWithUnderline(style) => {
+            write_boxed_with_underline(writer, renderer, text, line_width, style)
+        }
+        // TODO: not implemented
+        DecorationStyle::BoxWithOverline(style) => {
+            write_boxed(writer, renderer, text, line_width, style)
+        }
+        // TODO: not implemented
+        DecorationStyle::BoxWithUnderOverline(style) => {
+            write_boxed(writer, renderer, text, line_width, style)
+        }
+        DecorationStyle::Underline(style) => {
+            write_underlined(writer, renderer, text, line_width, style)
+        }
+        DecorationStyle::Overline(style) => {
+            write_overlined(writer, renderer, text, line_width, style)
+        }
+        DecorationStyle::UnderOverline(style) => {
+            write_underoverlined(writer, renderer, text, line_width, style)
+        }
+        DecorationStyle::NoDecoration => write_no_decoration(writer, text),
     }
-    Ok(())
+}
+
+fn write_no_decoration(writer: &mut dyn Write, text: &str) -> std::io::Result<()> {
+    writeln!(writer, "{}", text)
 }
 
 /// Write text to stream, surrounded by a box, leaving the cursor just
 /// beyond the bottom right corner.
-pub fn write_boxed(
+fn write_boxed<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     _line_width: &Width, // ignored
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     let up_left = if decoration_style.is_bold {
@@ -40,50 +63,35 @@ pub fn write_boxed(
     } else {
         box_drawing::light::UP_LEFT
     };
-    let box_width = UnicodeWidthStr::width(strip_ansi_codes(text).as_ref());
-    write_boxed_partial(
-        writer,
-        text,
-        raw_text,
-        box_width,
-        text_style,
-        decoration_style,
-    )?;
-    writeln!(writer, "{}", decoration_style.paint(up_left))?;
+    let box_width = measure_text_width(text);
+    write_boxed_partial(writer, renderer, text, box_width, decoration_style)?;
+    writeln!(writer, "{}", renderer.paint(decoration_style, up_left))?;
     Ok(())
 }
 
 /// Write text to stream, surrounded by a box, and extend a line from
 /// the bottom right corner.
-pub fn write_boxed_with_underline(
+fn write_boxed_with_underline<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     line_width: &Width,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
-    let box_width = UnicodeWidthStr::width(strip_ansi_codes(text).as_ref());
-    write_boxed_with_horizontal_whisker(
-        writer,
-        text,
-        raw_text,
-        box_width,
-        text_style,
-        decoration_style,
-    )?;
+    let box_width = measure_text_width(text);
+    write_boxed_with_horizontal_whisker(writer, renderer, text, box_width, decoration_style)?;
     let line_width = match *line_width {
         Width::Fixed(n) => n,
         Width::Variable => box_width,
     };
     write_horizontal_line(
         writer,
+        renderer,
         if line_width > box_width {
             line_width - box_width - 1
         } else {
             0
         },
-        text_style,
         decoration_style,
     )?;
     write!(writer, "\n")?;
@@ -96,80 +104,73 @@ enum UnderOverline {
     Underover,
 }
 
-pub fn write_underlined(
+fn write_underlined<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     line_width: &Width,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     _write_under_or_over_lined(
         UnderOverline::Under,
         writer,
+        renderer,
         text,
-        raw_text,
         line_width,
-        text_style,
         decoration_style,
     )
 }
 
-pub fn write_overlined(
+fn write_overlined<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     line_width: &Width,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     _write_under_or_over_lined(
         UnderOverline::Over,
         writer,
+        renderer,
         text,
-        raw_text,
         line_width,
-        text_style,
         decoration_style,
     )
 }
 
-pub fn write_underoverlined(
+fn write_underoverlined<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     line_width: &Width,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     _write_under_or_over_lined(
         UnderOverline::Underover,
         writer,
+        renderer,
         text,
-        raw_text,
         line_width,
-        text_style,
         decoration_style,
     )
 }
 
-fn _write_under_or_over_lined(
+fn _write_under_or_over_lined<R: Renderer + ?Sized>(
     underoverline: UnderOverline,
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     line_width: &Width,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
-    let text_width = UnicodeWidthStr::width(strip_ansi_codes(text).as_ref());
+    let text_width = measure_text_width(text);
     let line_width = match *line_width {
         Width::Fixed(n) => max(n, text_width),
         Width::Variable => text_width,
     };
     let mut write_line: Box<dyn FnMut(&mut dyn Write) -> std::io::Result<()>> =
         Box::new(|writer| {
-            write_horizontal_line(writer, line_width, text_style, decoration_style)?;
+            write_horizontal_line(writer, renderer, line_width, decoration_style)?;
             write!(writer, "\n")?;
             Ok(())
         });
@@ -177,11 +178,7 @@ fn _write_under_or_over_lined(
         UnderOverline::Under => {}
         _ => write_line(writer)?,
     }
-    if text_style.is_raw {
-        writeln!(writer, "{}", raw_text)?;
-    } else {
-        writeln!(writer, "{}", text_style.ansi_term_style.paint(text))?;
-    }
+    writeln!(writer, "{}", text)?;
     match underoverline {
         UnderOverline::Over => {}
         _ => write_line(writer)?,
@@ -189,10 +186,10 @@ fn _write_under_or_over_lined(
     Ok(())
 }
 
-fn write_horizontal_line(
+fn write_horizontal_line<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     width: usize,
-    _text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     let horizontal = if decoration_style.is_bold {
@@ -203,16 +200,15 @@ fn write_horizontal_line(
     write!(
         writer,
         "{}",
-        decoration_style.paint(horizontal.repeat(width))
+        renderer.paint(decoration_style, &horizontal.repeat(width))
     )
 }
 
-fn write_boxed_with_horizontal_whisker(
+fn write_boxed_with_horizontal_whisker<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     box_width: usize,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     let up_horizontal = if decoration_style.is_bold {
@@ -220,24 +216,20 @@ fn write_boxed_with_horizontal_whisker(
     } else {
         box_drawing::light::UP_HORIZONTAL
     };
-    write_boxed_partial(
+    write_boxed_partial(writer, renderer, text, box_width, decoration_style)?;
+    write!(
         writer,
-        text,
-        raw_text,
-        box_width,
-        text_style,
-        decoration_style,
+        "{}",
+        renderer.paint(decoration_style, up_horizontal)
     )?;
-    write!(writer, "{}", decoration_style.paint(up_horizontal))?;
     Ok(())
 }
 
-fn write_boxed_partial(
+fn write_boxed_partial<R: Renderer + ?Sized>(
     writer: &mut dyn Write,
+    renderer: &R,
     text: &str,
-    raw_text: &str,
     box_width: usize,
-    text_style: Style,
     decoration_style: ansi_term::Style,
 ) -> std::io::Result<()> {
     let (horizontal, down_left, vertical) = if decoration_style.is_bold {
@@ -257,18 +249,19 @@ fn write_boxed_partial(
     write!(
         writer,
         "{}{}\n",
-        decoration_style.paint(&horizontal_edge),
-        decoration_style.paint(down_left),
+        renderer.paint(decoration_style, &horizontal_edge),
+        renderer.paint(decoration_style, down_left),
     )?;
-    if text_style.is_raw {
-        write!(writer, "{}", raw_text)?;
-    } else {
-        write!(writer, "{}", text_style.ansi_term_style.paint(text))?;
-    }
+    write!(writer, "{}", text)?;
     write!(
         writer,
         "{}\n{}",
-        decoration_style.paint(vertical),
-        decoration_style.paint(&horizontal_edge),
+        renderer.paint(decoration_style, vertical),
+        renderer.paint(decoration_style, &horizontal_edge),
     )
 }
+
+/// Return the display width of text, ignoring ANSI color escape sequences and hyperlinks.
+fn measure_text_width(text: &str) -> usize {
+    UnicodeWidthStr::width(strip_ansi_codes(&hyperlinks::strip_osc8_hyperlinks(text)).as_ref())
+}
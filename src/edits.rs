@@ -8,7 +8,9 @@ use crate::align;
 /// Infer the edit operations responsible for the differences between a collection of old and new
 /// lines. A "line" is a string. An annotated line is a Vec of (op, &str) pairs, where the &str
 /// slices are slices of the line, and their concatenation equals the line. Return the input minus
-/// and plus lines, in annotated form.
+/// and plus lines, in annotated form, together with the line alignment: a sequence of (minus line
+/// index, plus line index) pairs, in output order, in which a homologous pair of lines has both
+/// indices set.
 pub fn infer_edits<'a, EditOperation>(
     minus_lines: &'a [String],
     plus_lines: &'a [String],
@@ -19,8 +21,9 @@ pub fn infer_edits<'a, EditOperation>(
     max_line_distance: f64,
     max_line_distance_for_naively_paired_lines: f64,
 ) -> (
-    Vec<Vec<(EditOperation, &'a str)>>, // annotated minus lines
-    Vec<Vec<(EditOperation, &'a str)>>, // annotated plus lines
+    Vec<Vec<(EditOperation, &'a str)>>,  // annotated minus lines
+    Vec<Vec<(EditOperation, &'a str)>>,  // annotated plus lines
+    Vec<(Option<usize>, Option<usize>)>, // line alignment
 )
 where
     EditOperation: Copy,
@@ -28,10 +31,11 @@ where
 {
     let mut annotated_minus_lines = Vec::<Vec<(EditOperation, &str)>>::new();
     let mut annotated_plus_lines = Vec::<Vec<(EditOperation, &str)>>::new();
+    let mut line_alignment = Vec::<(Option<usize>, Option<usize>)>::new();
 
     let mut emitted = 0; // plus lines emitted so far
 
-    'minus_lines_loop: for minus_line in minus_lines {
+    'minus_lines_loop: for (minus_index, minus_line) in minus_lines.iter().enumerate() {
         let mut considered = 0; // plus lines considered so far as match for minus_line
         for plus_line in &plus_lines[emitted..] {
             let alignment = align::Alignment::new(tokenize(minus_line), tokenize(plus_line));
@@ -51,12 +55,17 @@ where
                 // minus_line and plus_line are inferred to be a homologous pair.
 
                 // Emit as unpaired the plus lines already considered and rejected
-                for plus_line in &plus_lines[emitted..(emitted + considered)] {
+                for (i, plus_line) in plus_lines[emitted..(emitted + considered)]
+                    .iter()
+                    .enumerate()
+                {
                     annotated_plus_lines.push(vec![(noop_insertion, plus_line)]);
+                    line_alignment.push((None, Some(emitted + i)));
                 }
                 emitted += considered;
                 annotated_minus_lines.push(annotated_minus_line);
                 annotated_plus_lines.push(annotated_plus_line);
+                line_alignment.push((Some(minus_index), Some(emitted)));
                 emitted += 1;
 
                 // Greedy: move on to the next minus line.
@@ -67,13 +76,15 @@ where
         }
         // No homolog was found for minus i; emit as unpaired.
         annotated_minus_lines.push(vec![(noop_deletion, minus_line)]);
+        line_alignment.push((Some(minus_index), None));
     }
     // Emit any remaining plus lines
-    for plus_line in &plus_lines[emitted..] {
+    for (i, plus_line) in plus_lines[emitted..].iter().enumerate() {
         annotated_plus_lines.push(vec![(noop_insertion, plus_line)]);
+        line_alignment.push((None, Some(emitted + i)));
     }
 
-    (annotated_minus_lines, annotated_plus_lines)
+    (annotated_minus_lines, annotated_plus_lines, line_alignment)
 }
 
 lazy_static! {
@@ -569,6 +580,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_infer_edits_line_alignment() {
+        let minus_lines = vec!["aaaa a aaa", "bbbb b bbb", "cccc c ccc"]
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let plus_lines = vec!["bbbb ! bbb", "dddd d ddd", "cccc ! ccc"]
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let (_, _, line_alignment) = infer_edits(
+            &minus_lines,
+            &plus_lines,
+            MinusNoop,
+            Deletion,
+            PlusNoop,
+            Insertion,
+            0.66,
+            0.0,
+        );
+        assert_eq!(
+            line_alignment,
+            vec![
+                (Some(0), None),
+                (Some(1), Some(0)),
+                (None, Some(1)),
+                (Some(2), Some(2)),
+            ]
+        );
+    }
+
     fn assert_edits(
         minus_lines: Vec<&str>,
         plus_lines: Vec<&str>,
@@ -583,7 +625,7 @@ mod tests {
             .into_iter()
             .map(|s| s.to_string())
             .collect::<Vec<String>>();
-        let actual_edits = infer_edits(
+        let (annotated_minus_lines, annotated_plus_lines, _) = infer_edits(
             &minus_lines,
             &plus_lines,
             MinusNoop,
@@ -593,7 +635,10 @@ mod tests {
             max_line_distance,
             0.0,
         );
-        assert_eq!(actual_edits, expected_edits);
+        assert_eq!(
+            (annotated_minus_lines, annotated_plus_lines),
+            expected_edits
+        );
     }
 
     // Assert that no edits are inferred for the supplied minus and plus lines.
@@ -0,0 +1,731 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use bytelines::ByteLines;
+use console::strip_ansi_codes;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::parse::{self, Source, State};
+
+/// An element of a diff, as output by `git diff`, `git show`, `git log -p`, or `diff -u`.
+///
+/// Every line of input produces exactly one event, with two exceptions. FileHeader does not
+/// correspond to a line: it is produced when the paths of a file are known, before the first hunk
+/// of the file. And in a porcelain word diff, a WordDiffLine is produced from the lines holding the
+/// tokens of a line of the file. The text of all events has ANSI escape sequences removed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffEvent {
+    /// A `commit <hash>` line, starting the metadata of a commit.
+    CommitHeader { hash: String, line: String },
+    /// A line of commit metadata following the commit header, e.g. the author, date or message.
+    CommitMeta { line: String },
+    /// A line of file metadata, e.g. "diff --git a/x b/x", "index 8e1e71d..f7fa5b8", "--- a/x".
+    FileMeta { line: String },
+    /// The paths of the file whose hunks follow. The path of an added or removed file is
+    /// "/dev/null".
+    FileHeader {
+        minus_file: String,
+        plus_file: String,
+    },
+    /// A hunk header line. `line_numbers` holds a (start, count) pair for each parent, followed by
+    /// that of the plus file.
+    HunkHeader {
+        line: String,
+        code_fragment: String,
+        line_numbers: Vec<(usize, usize)>,
+    },
+    /// A removed line. `prefix` holds one column per parent.
+    MinusLine { prefix: String, content: String },
+    /// An added line.
+    PlusLine { prefix: String, content: String },
+    /// An unchanged line.
+    ZeroLine { prefix: String, content: String },
+    /// A line of the file in the output of `git diff --word-diff=plain` or
+    /// `--word-diff=porcelain`, as a sequence of (kind, text) tokens. The kind of a token is one
+    /// of ' ' (unchanged), '-' (removed) or '+' (added).
+    WordDiffLine { tokens: Vec<(char, String)> },
+    /// Any other line, e.g. a diffstat line or "\ No newline at end of file".
+    Other { line: String },
+}
+
+/// The format of the hunk lines, as selected by `git diff --word-diff`. This can only be detected
+/// from the hunk lines themselves, so it is Unknown until a hunk line distinguishes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WordDiffFormat {
+    Unknown,
+    Off,       // An ordinary line-based diff
+    Plain,     // `--word-diff=plain`: changes are marked inline as [-removed-] and {+added+}
+    Porcelain, // `--word-diff=porcelain`: one token per line, and a line "~" for each newline
+}
+
+lazy_static! {
+    static ref PLAIN_WORD_DIFF_MARKER_REGEX: Regex =
+        Regex::new(r"\[-(?P<minus>.*?)-\]|\{\+(?P<plus>.*?)\+\}").unwrap();
+}
+
+/// A streaming parser of diff events. This is independent of the painting of the diff: it does
+/// not depend on the configuration, and can be used by other programs that display diffs.
+///
+/// ```
+/// use git_delta::events::{DiffEvent, DiffEventParser};
+///
+/// let diff = "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n";
+/// let mut added = Vec::new();
+/// for event in DiffEventParser::new(diff.as_bytes()) {
+///     if let DiffEvent::PlusLine { content, .. } = event? {
+///         added.push(content);
+///     }
+/// }
+/// assert_eq!(added, vec!["b"]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct DiffEventParser<R: BufRead> {
+    lines: ByteLines<R>,
+    state: State,
+    source: Source,
+    minus_file: String,
+    plus_file: String,
+    is_file_header_pending: bool,
+    n_parents: usize,
+    // The number of lines of each parent, and of the plus file, remaining in the current hunk.
+    hunk_remaining_line_counts: Vec<usize>,
+    word_diff_format: WordDiffFormat,
+    // Hunk lines without a hunk line prefix, held back until the word diff format is known.
+    pending_hunk_lines: Vec<(String, String)>,
+    // The tokens of the current line of a porcelain word diff.
+    word_diff_tokens: Vec<(char, String)>,
+    // Each event, together with the line of input (with ANSI escape sequences) that produced it.
+    events: VecDeque<(DiffEvent, String)>,
+}
+
+impl<R: BufRead> DiffEventParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::from_byte_lines(ByteLines::new(reader))
+    }
+
+    pub fn from_byte_lines(lines: ByteLines<R>) -> Self {
+        Self {
+            lines,
+            state: State::Unknown,
+            source: Source::Unknown,
+            minus_file: "".to_string(),
+            plus_file: "".to_string(),
+            is_file_header_pending: false,
+            n_parents: 1,
+            hunk_remaining_line_counts: Vec::new(),
+            word_diff_format: WordDiffFormat::Unknown,
+            pending_hunk_lines: Vec::new(),
+            word_diff_tokens: Vec::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Return the next event, together with the line of input that produced it, before removal of
+    /// ANSI escape sequences. The line is empty for an event that does not correspond to a single
+    /// line: a FileHeader, or the WordDiffLine of a porcelain word diff.
+    pub fn next_with_raw_line(&mut self) -> Option<std::io::Result<(DiffEvent, String)>> {
+        while self.events.is_empty() {
+            match self.lines.next() {
+                Some(Ok(raw_line_bytes)) => {
+                    let raw_line = String::from_utf8_lossy(raw_line_bytes).to_string();
+                    let line = strip_ansi_codes(&raw_line).to_string();
+                    self.handle_line(line, raw_line);
+                }
+                Some(Err(error)) => return Some(Err(error)),
+                None => {
+                    self.flush_pending_hunk_lines();
+                    if !self.word_diff_tokens.is_empty() {
+                        self.flush_word_diff_tokens();
+                    }
+                    self.flush_file_header();
+                    break;
+                }
+            }
+        }
+        self.events.pop_front().map(Ok)
+    }
+
+    fn handle_line(&mut self, line: String, raw_line: String) {
+        if self.source == Source::Unknown {
+            self.source = parse::detect_source(&line);
+        }
+        if self.is_in_open_hunk(&line) {
+            self.handle_hunk_line(line, raw_line);
+            return;
+        }
+        if self.state.is_in_hunk() && self.word_diff_format == WordDiffFormat::Unknown {
+            // The first hunk has ended without any line that could only come from a word diff.
+            self.flush_pending_hunk_lines();
+            self.word_diff_format = WordDiffFormat::Off;
+        }
+        if let Some(rest) = line.strip_prefix("commit ") {
+            self.flush_file_header();
+            self.state = State::CommitMeta;
+            let hash = rest.split_whitespace().next().unwrap_or("").to_string();
+            self.push_event(DiffEvent::CommitHeader { hash, line }, raw_line);
+        } else if line.starts_with("diff ")
+            || self.source == Source::DiffUnified
+                && self.state != State::FileMeta
+                && line.starts_with("--- ")
+        {
+            // A `diff -u` file section may have no "diff" line, and start with the "---" line.
+            self.flush_file_header();
+            self.state = State::FileMeta;
+            self.is_file_header_pending = true;
+            self.minus_file = parse::get_old_path_from_diff_line(&line).unwrap_or_default();
+            self.plus_file = parse::get_new_path_from_diff_line(&line).unwrap_or_default();
+            self.handle_file_meta_line(line, raw_line);
+        } else if self.state == State::FileMeta && !line.starts_with("@@") {
+            self.handle_file_meta_line(line, raw_line);
+        } else if line.starts_with("@@") {
+            self.flush_file_header();
+            self.state = State::HunkHeader;
+            self.n_parents = parse::get_number_of_parents_from_hunk_header_line(&line);
+            let (code_fragment, line_numbers) = parse::parse_hunk_metadata(&line);
+            self.hunk_remaining_line_counts = line_numbers.iter().map(|(_, n)| *n).collect();
+            self.push_event(
+                DiffEvent::HunkHeader {
+                    code_fragment: code_fragment.to_string(),
+                    line_numbers,
+                    line,
+                },
+                raw_line,
+            );
+        } else if self.state == State::CommitMeta {
+            self.push_event(DiffEvent::CommitMeta { line }, raw_line);
+        } else {
+            self.push_event(DiffEvent::Other { line }, raw_line);
+        }
+    }
+
+    /// Is the line part of the current hunk? The lines of an ordinary hunk are counted, since the
+    /// hunk header gives the number of lines. This does not work for a word diff, whose hunk lines
+    /// continue until the next hunk, file or commit.
+    fn is_in_open_hunk(&self, line: &str) -> bool {
+        self.state.is_in_hunk()
+            && !(line.starts_with("@@") || line.starts_with("diff ") || line.starts_with("commit "))
+            && match self.word_diff_format {
+                WordDiffFormat::Plain | WordDiffFormat::Porcelain => true,
+                // The "~" ending the first line of a porcelain word diff may follow the last line
+                // counted by the hunk header.
+                _ => {
+                    self.hunk_remaining_line_counts.iter().any(|n| *n > 0)
+                        || !self.pending_hunk_lines.is_empty()
+                        || line == "~"
+                }
+            }
+    }
+
+    fn handle_file_meta_line(&mut self, line: String, raw_line: String) {
+        let is_git_diff = self.source == Source::GitDiff;
+        let is_plus_file_line = line.starts_with("+++ ");
+        if line.starts_with("--- ") || line.starts_with("rename from ") {
+            self.minus_file = parse::get_file_path_from_file_meta_line(&line, is_git_diff);
+        } else if is_plus_file_line || line.starts_with("rename to ") {
+            self.plus_file = parse::get_file_path_from_file_meta_line(&line, is_git_diff);
+        } else if line.starts_with("Binary files ") {
+            // A binary file has no hunks: this line takes their place.
+            self.flush_file_header();
+        }
+        self.push_event(DiffEvent::FileMeta { line }, raw_line);
+        if is_plus_file_line {
+            self.flush_file_header();
+        }
+    }
+
+    fn handle_hunk_line(&mut self, line: String, raw_line: String) {
+        match self.word_diff_format {
+            WordDiffFormat::Plain => {
+                let tokens = parse_plain_word_diff_line(&line);
+                self.push_event(DiffEvent::WordDiffLine { tokens }, raw_line);
+                return;
+            }
+            WordDiffFormat::Porcelain => {
+                self.handle_porcelain_word_diff_line(line, raw_line);
+                return;
+            }
+            WordDiffFormat::Unknown if self.source == Source::GitDiff && self.n_parents == 1 => {
+                if line == "~" {
+                    // This only occurs in a porcelain word diff. The tokens of the first line of
+                    // the word diff have already been produced as ordinary hunk lines.
+                    self.flush_pending_hunk_lines();
+                    self.word_diff_format = WordDiffFormat::Porcelain;
+                    return;
+                } else if PLAIN_WORD_DIFF_MARKER_REGEX.is_match(&line) {
+                    // A plain word diff line may also start with ' ', '-' or '+' (e.g. a Markdown
+                    // list item), so this does not depend on the first character of the line.
+                    self.word_diff_format = WordDiffFormat::Plain;
+                    for (pending_line, pending_raw_line) in
+                        std::mem::take(&mut self.pending_hunk_lines)
+                    {
+                        let tokens = parse_plain_word_diff_line(&pending_line);
+                        self.push_event(DiffEvent::WordDiffLine { tokens }, pending_raw_line);
+                    }
+                    let tokens = parse_plain_word_diff_line(&line);
+                    self.push_event(DiffEvent::WordDiffLine { tokens }, raw_line);
+                    return;
+                } else if !line.starts_with(&[' ', '-', '+', '\\'][..]) {
+                    // A line without a hunk line prefix is held back until a later line of the
+                    // hunk identifies whether the diff is a word diff.
+                    self.pending_hunk_lines.push((line, raw_line));
+                    return;
+                }
+                self.flush_pending_hunk_lines();
+            }
+            _ => {}
+        }
+        let state = match parse::get_hunk_line_state(&line, self.n_parents) {
+            Some(state) => state,
+            None => {
+                self.push_event(DiffEvent::Other { line }, raw_line);
+                return;
+            }
+        };
+        let prefix = line[..self.n_parents].to_string();
+        // A line is present in a parent unless it has '+' in that parent's column, and in the plus
+        // file unless it has '-' in any column.
+        let n_parents = self.n_parents;
+        for (i, count) in self.hunk_remaining_line_counts.iter_mut().enumerate() {
+            let is_present = if i < n_parents {
+                prefix.as_bytes()[i] != b'+'
+            } else {
+                !prefix.contains('-')
+            };
+            if is_present {
+                *count = count.saturating_sub(1);
+            }
+        }
+        let content = line[self.n_parents..].to_string();
+        self.push_event(
+            match state {
+                State::HunkMinus => DiffEvent::MinusLine { prefix, content },
+                State::HunkPlus => DiffEvent::PlusLine { prefix, content },
+                _ => DiffEvent::ZeroLine { prefix, content },
+            },
+            raw_line,
+        );
+        self.state = state;
+    }
+
+    /// Accumulate the tokens of a line of a porcelain word diff, which is terminated by "~".
+    fn handle_porcelain_word_diff_line(&mut self, line: String, raw_line: String) {
+        if line == "~" {
+            self.flush_word_diff_tokens();
+        } else if line.starts_with(&[' ', '-', '+'][..]) {
+            let kind = line.chars().next().unwrap();
+            self.word_diff_tokens.push((kind, line[1..].to_string()));
+        } else {
+            self.push_event(DiffEvent::Other { line }, raw_line);
+        }
+    }
+
+    fn flush_word_diff_tokens(&mut self) {
+        let tokens = std::mem::take(&mut self.word_diff_tokens);
+        self.push_event(DiffEvent::WordDiffLine { tokens }, "".to_string());
+    }
+
+    /// Produce the hunk lines that were held back while the word diff format was unknown. They
+    /// turned out not to be word diff lines, and have no hunk line prefix.
+    fn flush_pending_hunk_lines(&mut self) {
+        for (line, raw_line) in std::mem::take(&mut self.pending_hunk_lines) {
+            self.push_event(DiffEvent::Other { line }, raw_line);
+        }
+    }
+
+    /// Produce the FileHeader event of the current file, if it has not already been produced.
+    fn flush_file_header(&mut self) {
+        if self.is_file_header_pending {
+            self.is_file_header_pending = false;
+            self.push_event(
+                DiffEvent::FileHeader {
+                    minus_file: self.minus_file.clone(),
+                    plus_file: self.plus_file.clone(),
+                },
+                "".to_string(),
+            );
+        }
+    }
+
+    fn push_event(&mut self, event: DiffEvent, raw_line: String) {
+        self.events.push_back((event, raw_line));
+    }
+}
+
+impl<R: BufRead> Iterator for DiffEventParser<R> {
+    type Item = std::io::Result<DiffEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_raw_line()
+            .map(|result| result.map(|(event, _)| event))
+    }
+}
+
+/// Parse a line of `--word-diff=plain` output into tokens.
+fn parse_plain_word_diff_line(line: &str) -> Vec<(char, String)> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    for captures in PLAIN_WORD_DIFF_MARKER_REGEX.captures_iter(line) {
+        let marker = captures.get(0).unwrap();
+        if marker.start() > offset {
+            tokens.push((' ', line[offset..marker.start()].to_string()));
+        }
+        match (captures.name("minus"), captures.name("plus")) {
+            (Some(text), _) => tokens.push(('-', text.as_str().to_string())),
+            (_, Some(text)) => tokens.push(('+', text.as_str().to_string())),
+            _ => {}
+        }
+        offset = marker.end();
+    }
+    if offset < line.len() {
+        tokens.push((' ', line[offset..].to_string()));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_events(input: &str) -> Vec<DiffEvent> {
+        DiffEventParser::new(input.as_bytes())
+            .map(|event| event.unwrap())
+            .collect()
+    }
+
+    fn line(text: &str) -> String {
+        text.to_string()
+    }
+
+    #[test]
+    fn test_git_show_events() {
+        let events = get_events(GIT_SHOW);
+        assert_eq!(
+            events,
+            vec![
+                DiffEvent::CommitHeader {
+                    hash: line("94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"),
+                    line: line("commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"),
+                },
+                DiffEvent::CommitMeta {
+                    line: line("Author: Dan Davison <dandavison7@gmail.com>")
+                },
+                DiffEvent::CommitMeta { line: line("") },
+                DiffEvent::CommitMeta {
+                    line: line("    Edit module")
+                },
+                DiffEvent::CommitMeta { line: line("") },
+                DiffEvent::FileMeta {
+                    line: line("diff --git a/src/main.rs b/src/main.rs")
+                },
+                DiffEvent::FileMeta {
+                    line: line("index 8e1e71d..f7fa5b8 100644")
+                },
+                DiffEvent::FileMeta {
+                    line: line("--- a/src/main.rs")
+                },
+                DiffEvent::FileMeta {
+                    line: line("+++ b/src/main.rs")
+                },
+                DiffEvent::FileHeader {
+                    minus_file: line("src/main.rs"),
+                    plus_file: line("src/main.rs"),
+                },
+                DiffEvent::HunkHeader {
+                    line: line("@@ -1,3 +1,3 @@ fn main() {"),
+                    code_fragment: line(" fn main() {"),
+                    line_numbers: vec![(1, 3), (1, 3)],
+                },
+                DiffEvent::ZeroLine {
+                    prefix: line(" "),
+                    content: line("    let x = 1;"),
+                },
+                DiffEvent::MinusLine {
+                    prefix: line("-"),
+                    content: line("    let y = 2;"),
+                },
+                DiffEvent::PlusLine {
+                    prefix: line("+"),
+                    content: line("    let y = 3;"),
+                },
+                DiffEvent::Other {
+                    line: line("\\ No newline at end of file")
+                },
+                DiffEvent::ZeroLine {
+                    prefix: line(" "),
+                    content: line("}"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_header_of_rename_without_hunks() {
+        let events = get_events(GIT_DIFF_RENAME);
+        let file_headers: Vec<&DiffEvent> = events
+            .iter()
+            .filter(|event| matches!(event, DiffEvent::FileHeader { .. }))
+            .collect();
+        assert_eq!(
+            file_headers,
+            vec![
+                &DiffEvent::FileHeader {
+                    minus_file: line("src/old.rs"),
+                    plus_file: line("src/new.rs"),
+                },
+                &DiffEvent::FileHeader {
+                    minus_file: line("/dev/null"),
+                    plus_file: line("src/added.rs"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lines_after_complete_hunk_are_not_hunk_lines() {
+        let events = get_events(GIT_FORMAT_PATCH);
+        assert_eq!(
+            &events[events.len() - 2..],
+            &[
+                DiffEvent::Other { line: line("-- ") },
+                DiffEvent::Other {
+                    line: line("2.26.2")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_unified_and_ansi_escape_sequences() {
+        let events = get_events(
+            "\x1b[1m--- a.txt\t2020-05-30 11:00:00\x1b[m\n\
+             +++ b.txt\t2020-05-30 11:00:01\n\
+             @@ -1 +1 @@\n\
+             -a\n\
+             +b\n",
+        );
+        assert_eq!(
+            events[2],
+            DiffEvent::FileHeader {
+                minus_file: line("a.txt"),
+                plus_file: line("b.txt"),
+            }
+        );
+        assert_eq!(
+            events[5],
+            DiffEvent::PlusLine {
+                prefix: line("+"),
+                content: line("b"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_plain_word_diff_line() {
+        assert_eq!(
+            parse_plain_word_diff_line("a [-b-]{+c+} d"),
+            vec![
+                (' ', line("a ")),
+                ('-', line("b")),
+                ('+', line("c")),
+                (' ', line(" d")),
+            ]
+        );
+        assert_eq!(
+            parse_plain_word_diff_line("[-a b-]"),
+            vec![('-', line("a b"))]
+        );
+    }
+
+    #[test]
+    fn test_plain_word_diff_events() {
+        let events = get_events(PLAIN_WORD_DIFF);
+        assert_eq!(
+            &events[6..],
+            &[
+                DiffEvent::WordDiffLine {
+                    tokens: vec![(' ', line("Some items:"))]
+                },
+                DiffEvent::WordDiffLine {
+                    tokens: vec![
+                        (' ', line("- item ")),
+                        ('-', line("two")),
+                        ('+', line("three")),
+                    ]
+                },
+                DiffEvent::WordDiffLine {
+                    tokens: vec![(' ', line("More text."))]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_porcelain_word_diff_events() {
+        let events = get_events(PORCELAIN_WORD_DIFF);
+        assert_eq!(
+            &events[6..],
+            &[
+                DiffEvent::ZeroLine {
+                    prefix: line(" "),
+                    content: line("The quick brown fox"),
+                },
+                DiffEvent::WordDiffLine {
+                    tokens: vec![
+                        (' ', line("jumps over the ")),
+                        ('-', line("lazy")),
+                        ('+', line("sleepy")),
+                        (' ', line(" dog.")),
+                    ]
+                },
+                DiffEvent::WordDiffLine {
+                    tokens: vec![('+', line("An added line."))]
+                },
+            ]
+        );
+    }
+
+    /// A reader whose input has not yet arrived.
+    struct PendingInput;
+
+    impl std::io::Read for PendingInput {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "input not yet available",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_ordinary_hunk_lines_are_not_held_back() {
+        use std::io::Read;
+
+        let input = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,3 @@
+-a
++b
+";
+        let reader = std::io::BufReader::new(input.as_bytes().chain(PendingInput));
+        let mut parser = DiffEventParser::new(reader);
+        let events: Vec<DiffEvent> = (0..7).map(|_| parser.next().unwrap().unwrap()).collect();
+        assert_eq!(
+            events[6],
+            DiffEvent::PlusLine {
+                prefix: line("+"),
+                content: line("b"),
+            }
+        );
+        assert!(parser.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_raw_lines() {
+        let mut parser = DiffEventParser::new(
+            "diff --git a/a.txt b/a.txt\n\
+             --- a/a.txt\n\
+             +++ b/a.txt\n\
+             @@ -1 +1 @@\n\
+             \x1b[31m-a\x1b[m\n"
+                .as_bytes(),
+        );
+        let events: Vec<(DiffEvent, String)> =
+            std::iter::from_fn(|| parser.next_with_raw_line().map(|event| event.unwrap()))
+                .collect();
+        assert_eq!(
+            events[3],
+            (
+                DiffEvent::FileHeader {
+                    minus_file: line("a.txt"),
+                    plus_file: line("a.txt"),
+                },
+                line("")
+            )
+        );
+        assert_eq!(
+            events[5],
+            (
+                DiffEvent::MinusLine {
+                    prefix: line("-"),
+                    content: line("a"),
+                },
+                line("\x1b[31m-a\x1b[m")
+            )
+        );
+    }
+
+    const GIT_SHOW: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+
+    Edit module
+
+diff --git a/src/main.rs b/src/main.rs
+index 8e1e71d..f7fa5b8 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@ fn main() {
+     let x = 1;
+-    let y = 2;
++    let y = 3;
+\\ No newline at end of file
+ }
+";
+
+    const GIT_DIFF_RENAME: &str = "\
+diff --git a/src/old.rs b/src/new.rs
+similarity index 100%
+rename from src/old.rs
+rename to src/new.rs
+diff --git a/src/added.rs b/src/added.rs
+new file mode 100644
+index 0000000..8e1e71d
+--- /dev/null
++++ b/src/added.rs
+@@ -0,0 +1 @@
++fn f() {}
+";
+
+    const GIT_FORMAT_PATCH: &str = "\
+diff --git a/a.txt b/a.txt
+index 8e1e71d..f7fa5b8 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-a
++b
+-- 
+2.26.2
+";
+
+    const PLAIN_WORD_DIFF: &str = "\
+diff --git a/doc.md b/doc.md
+index 8e1e71d..f7fa5b8 100644
+--- a/doc.md
++++ b/doc.md
+@@ -1,3 +1,3 @@
+Some items:
+- item [-two-]{+three+}
+More text.
+";
+
+    const PORCELAIN_WORD_DIFF: &str = "\
+diff --git a/doc.md b/doc.md
+index 8e1e71d..f7fa5b8 100644
+--- a/doc.md
++++ b/doc.md
+@@ -1,2 +1,3 @@
+ The quick brown fox
+~
+ jumps over the\x20
+-lazy
++sleepy
+  dog.
+~
++An added line.
+~
+";
+}
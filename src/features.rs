@@ -0,0 +1,132 @@
+/// Features are named sets of option values. A user defines a feature in a subsection of the
+/// [delta] section of git config, e.g. [delta "review"], and activates it with --features=review
+/// (or the DELTA_FEATURES environment variable, or the features key of the [delta] section).
+/// Delta also has builtin features, which a user may modify by defining a subsection of the same
+/// name: options set there take precedence over the builtin values.
+use crate::env;
+use crate::git_config::{GitConfig, GitConfigGet};
+
+/// The builtin features, with the values they give to options.
+const BUILTIN_FEATURES: &[(&str, &[(&str, &str)])] = &[(
+    // --color-only: only add color to the input; do not alter it in any other way. This is
+    // intended for use with `git add -p`, which requires that each line of delta's output
+    // corresponds to exactly one line of git's.
+    "color-only",
+    &[
+        ("keep-plus-minus-markers", "true"),
+        ("tabs", "0"),
+        ("commit-style", "raw"),
+        ("commit-decoration-style", "none"),
+        ("file-style", "raw"),
+        ("file-decoration-style", "none"),
+        ("hunk-header-style", "raw"),
+        ("hunk-header-decoration-style", "none"),
+    ],
+)];
+
+/// Return the names of the active features, in increasing order of precedence. The list is taken
+/// from the first of these that is present: the --features command line option, the
+/// DELTA_FEATURES environment variable, and the features key of the [delta] section of git
+/// config. Feature names are separated by whitespace. If --color-only is in effect, then the
+/// color-only feature precedes the others.
+pub fn get_features(
+    features_from_command_line: Option<&str>,
+    color_only: bool,
+    git_config: Option<&GitConfig>,
+) -> Vec<String> {
+    let features = features_from_command_line
+        .map(|s| s.to_string())
+        .or_else(|| env::get_env_var("DELTA_FEATURES"))
+        .or_else(|| git_config.and_then(|git_config| git_config.get("delta.features")))
+        .unwrap_or_default();
+    let mut features: Vec<String> = features.split_whitespace().map(String::from).collect();
+    if color_only && !features.iter().any(|feature| feature == "color-only") {
+        features.insert(0, "color-only".to_string());
+    }
+    features
+}
+
+/// Return the value of an option, as given by the last of the active features that sets it, or
+/// by the [delta] section of git config if none of them does.
+pub fn get_option_value<T: GitConfigGet>(
+    option_name: &str,
+    features: &[String],
+    git_config: Option<&GitConfig>,
+) -> Option<T> {
+    for feature in features.iter().rev() {
+        let key = format!("delta.{}.{}", feature, option_name);
+        if let Some(value) = git_config.and_then(|git_config| git_config.get(&key)) {
+            return Some(value);
+        }
+        if let Some(value) = get_builtin_feature_value(feature, option_name) {
+            return Some(T::from_git_config_value(&key, value));
+        }
+    }
+    git_config.and_then(|git_config| git_config.get(&format!("delta.{}", option_name)))
+}
+
+fn get_builtin_feature_value(feature: &str, option_name: &str) -> Option<&'static str> {
+    BUILTIN_FEATURES
+        .iter()
+        .find(|(name, _)| *name == feature)
+        .and_then(|(_, values)| values.iter().find(|(name, _)| *name == option_name))
+        .map(|(_, value)| *value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_features, get_option_value};
+    use crate::git_config::GitConfig;
+
+    #[test]
+    fn test_get_features() {
+        let git_config = GitConfig::parse("delta.features\nreview\0");
+        assert_eq!(
+            get_features(Some("a b"), false, Some(&git_config)),
+            ["a", "b"]
+        );
+        assert_eq!(get_features(None, false, Some(&git_config)), ["review"]);
+        assert_eq!(
+            get_features(None, true, Some(&git_config)),
+            ["color-only", "review"]
+        );
+        assert_eq!(get_features(None, false, None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_later_features_take_precedence() {
+        let git_config = GitConfig::parse(
+            "delta.file-style\nblue\0\
+             delta.presentation.file-style\nred\0\
+             delta.presentation.side-by-side\ntrue\0\
+             delta.dense.file-style\ngreen\0",
+        );
+        let features = vec!["presentation".to_string(), "dense".to_string()];
+        let get = |option_name, features: &[String]| {
+            get_option_value::<String>(option_name, features, Some(&git_config))
+        };
+        assert_eq!(get("file-style", &features), Some("green".to_string()));
+        assert_eq!(get("file-style", &features[..1]), Some("red".to_string()));
+        assert_eq!(get("file-style", &[]), Some("blue".to_string()));
+        assert_eq!(get("side-by-side", &features), Some("true".to_string()));
+        assert_eq!(get("width", &features), None);
+    }
+
+    #[test]
+    fn test_builtin_features_can_be_modified_in_git_config() {
+        let git_config = GitConfig::parse("delta.color-only.file-style\nred\0");
+        let features = vec!["color-only".to_string()];
+        assert_eq!(
+            get_option_value::<String>("file-style", &features, Some(&git_config)),
+            Some("red".to_string())
+        );
+        assert_eq!(
+            get_option_value::<String>("hunk-header-style", &features, None),
+            Some("raw".to_string())
+        );
+        assert_eq!(
+            get_option_value::<usize>("tabs", &features, Some(&git_config)),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Return the root directory of the git repository containing the current directory, or None if
+/// the current directory is not in a git repository.
+pub fn get_git_root() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim_end(),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Return the contents of a blob in the object database of the current repository, or None if git
+/// could not read it, e.g. because its hash is that of a file in the working tree that has not
+/// been added.
+pub fn get_blob_contents(blob_hash: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["cat-file", "blob", blob_hash])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+/// Return the hash of the blob that the index holds for the file at `path`, relative to the root
+/// of the repository, or None if the index has no such file.
+pub fn get_index_blob_hash(path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", &format!(":{}", path)])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_string(),
+        )
+    } else {
+        None
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::process;
+use std::process::Command;
+
+/// The entries of the `[delta]` section (and its subsections) of the git config files, as
+/// returned by `git config`. Keys are full git config keys, e.g. "delta.side-by-side".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GitConfig {
+    entries: HashMap<String, Vec<String>>,
+}
+
+impl GitConfig {
+    /// Read the delta entries from the system, global and repository git config files, with the
+    /// usual git precedence. Return None if git could not be run.
+    pub fn try_create() -> Option<Self> {
+        let output = Command::new("git")
+            .args(["config", "--null", "--get-regexp", r"^delta\."])
+            .output()
+            .ok()?;
+        // `git config --get-regexp` exits with status 1 if no key matches.
+        if !output.status.success() && output.status.code() != Some(1) {
+            return None;
+        }
+        Some(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse the output of `git config --null --get-regexp`: a sequence of NUL-terminated
+    /// entries, each of which is a key, followed by a newline and the value if there is one. A
+    /// key may occur more than once: for a single-valued option the last value wins, as it does
+    /// for git, and a multi-valued option takes all of them.
+    pub fn parse(text: &str) -> Self {
+        let mut entries = HashMap::<String, Vec<String>>::new();
+        for entry in text.split('\0').filter(|entry| !entry.is_empty()) {
+            let (key, value) = match entry.find('\n') {
+                Some(i) => (&entry[..i], &entry[i + 1..]),
+                // A key without a value is a boolean true.
+                None => (entry, "true"),
+            };
+            entries
+                .entry(key.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+        Self { entries }
+    }
+
+    pub fn get<T: GitConfigGet>(&self, key: &str) -> Option<T> {
+        self.entries
+            .get(key)
+            .map(|values| T::from_git_config_values(key, values))
+    }
+}
+
+/// Conversion of a git config value to the type of a command line option.
+pub trait GitConfigGet: Sized {
+    fn from_git_config_value(key: &str, value: &str) -> Self;
+
+    fn from_git_config_values(key: &str, values: &[String]) -> Self {
+        Self::from_git_config_value(key, values.last().unwrap())
+    }
+}
+
+impl GitConfigGet for String {
+    fn from_git_config_value(_key: &str, value: &str) -> Self {
+        value.to_string()
+    }
+}
+
+impl GitConfigGet for Option<String> {
+    fn from_git_config_value(_key: &str, value: &str) -> Self {
+        Some(value.to_string())
+    }
+}
+
+impl GitConfigGet for Vec<String> {
+    fn from_git_config_value(_key: &str, value: &str) -> Self {
+        vec![value.to_string()]
+    }
+
+    fn from_git_config_values(_key: &str, values: &[String]) -> Self {
+        values.to_vec()
+    }
+}
+
+impl GitConfigGet for bool {
+    fn from_git_config_value(key: &str, value: &str) -> Self {
+        match value.to_lowercase().as_ref() {
+            "true" | "yes" | "on" | "1" => true,
+            "false" | "no" | "off" | "0" | "" => false,
+            _ => exit_with_invalid_value_error(key, value, "a boolean"),
+        }
+    }
+}
+
+impl GitConfigGet for usize {
+    fn from_git_config_value(key: &str, value: &str) -> Self {
+        value
+            .parse()
+            .unwrap_or_else(|_| exit_with_invalid_value_error(key, value, "an integer"))
+    }
+}
+
+impl GitConfigGet for f64 {
+    fn from_git_config_value(key: &str, value: &str) -> Self {
+        value
+            .parse()
+            .unwrap_or_else(|_| exit_with_invalid_value_error(key, value, "a number"))
+    }
+}
+
+fn exit_with_invalid_value_error(key: &str, value: &str, expected: &str) -> ! {
+    eprintln!(
+        "Invalid value for {} in git config: {} (expected {})",
+        key, value, expected
+    );
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitConfig;
+
+    #[test]
+    fn test_parse() {
+        let git_config = GitConfig::parse(
+            "delta.side-by-side\0delta.minus-style\nred bold\0delta.tabs\n8\0delta.tabs\n2\0",
+        );
+        assert_eq!(git_config.get::<bool>("delta.side-by-side"), Some(true));
+        assert_eq!(
+            git_config.get::<String>("delta.minus-style"),
+            Some("red bold".to_string())
+        );
+        assert_eq!(git_config.get::<usize>("delta.tabs"), Some(2));
+        assert_eq!(git_config.get::<usize>("delta.width"), None);
+        assert_eq!(
+            git_config.get::<Vec<String>>("delta.tabs"),
+            Some(vec!["8".to_string(), "2".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline_value() {
+        let git_config = GitConfig::parse("delta.commit-style\nraw\nmore\0");
+        assert_eq!(
+            git_config.get::<String>("delta.commit-style"),
+            Some("raw\nmore".to_string())
+        );
+    }
+}
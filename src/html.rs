@@ -0,0 +1,437 @@
+use std::io::{BufRead, Write};
+
+use ansi_term::{self, ANSIString, Color};
+use bytelines::ByteLines;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::bat::terminal::to_ansi_color;
+use crate::config::{Config, Width};
+use crate::delta::delta;
+use crate::render::Renderer;
+use crate::style::DecorationStyle;
+
+lazy_static! {
+    static ref ESCAPE_SEQUENCE_REGEX: Regex = Regex::new(
+        r"\x1b\[(?P<sgr>[0-9;]*)m|\x1b\[[0-9;]*[A-Za-z]|\x1b\]8;[^;\x1b\x07]*;(?P<url>[^\x1b\x07]*)(?:\x1b\\|\x07)"
+    )
+    .unwrap();
+}
+
+/// Write the diff as a single self-contained HTML document. The lines are painted exactly as they
+/// are for the terminal, but each style section is written as a `<span>` with an inline CSS style
+/// instead of ANSI escape sequences, and the page background is the background of the theme.
+pub fn write_html<I>(
+    lines: ByteLines<I>,
+    writer: &mut dyn Write,
+    config: &Config,
+) -> std::io::Result<()>
+where
+    I: BufRead,
+{
+    let (foreground, background) = get_theme_colors(config);
+    writeln!(
+        writer,
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>delta</title>\n\
+         </head>\n\
+         <body style=\"margin:0;--foreground:{foreground};--background:{background};\
+         color:var(--foreground);background-color:var(--background)\">\n\
+         <pre style=\"margin:0;padding:1em;font-family:monospace\">",
+        foreground = foreground,
+        background = background,
+    )?;
+    delta(lines, writer, config)?;
+    writeln!(writer, "</pre>\n</body>\n</html>")
+}
+
+/// Return the CSS foreground and background colors of the page, taken from the theme if it has
+/// them.
+fn get_theme_colors(config: &Config) -> (String, String) {
+    let settings = config.theme.as_ref().map(|theme| &theme.settings);
+    let is_light_mode = config.is_light_mode;
+    let get_color =
+        |color: Option<syntect::highlighting::Color>, light: &str, dark: &str| match color {
+            Some(color) => get_css_color(to_ansi_color(color, true)),
+            None if is_light_mode => light.to_string(),
+            None => dark.to_string(),
+        };
+    (
+        get_color(
+            settings.and_then(|settings| settings.foreground),
+            "#000000",
+            "#ffffff",
+        ),
+        get_color(
+            settings.and_then(|settings| settings.background),
+            "#ffffff",
+            "#000000",
+        ),
+    )
+}
+
+/// HTML spans with inline CSS styles. Decorations are drawn with CSS borders, the equivalents of
+/// the box-drawing characters used in the terminal.
+pub struct MarkupRenderer;
+
+impl Renderer for MarkupRenderer {
+    fn paint(&self, style: ansi_term::Style, text: &str) -> String {
+        paint(&style, &ansi_to_html(text))
+    }
+
+    fn paint_ansi_text(&self, text: &str) -> String {
+        ansi_to_html(text)
+    }
+
+    fn paint_sections(&self, sections: &[ANSIString]) -> String {
+        sections
+            .iter()
+            .map(|section| paint(section.style_ref(), &ansi_to_html(section)))
+            .collect()
+    }
+
+    /// If the line's background color extends to the terminal width then the line is a block
+    /// element with that background color, which is the equivalent of filling rightwards with
+    /// ANSI_CSI_ERASE_IN_LINE.
+    fn paint_line(&self, sections: &[ANSIString], right_fill_background: Option<Color>) -> String {
+        let spans = self.paint_sections(sections);
+        match right_fill_background {
+            Some(color) => format!(
+                "<span style=\"display:block;background-color:{}\">{}\n</span>",
+                get_css_color(color),
+                spans
+            ),
+            None => format!("{}\n", spans),
+        }
+    }
+
+    fn write_decorated(
+        &self,
+        writer: &mut dyn Write,
+        text: &str,
+        line_width: &Width,
+        decoration_style: DecorationStyle,
+    ) -> std::io::Result<()> {
+        let width = match *line_width {
+            Width::Fixed(n) => format!(";min-width:{}ch", n),
+            Width::Variable => "".to_string(),
+        };
+        match decoration_style {
+            // As in the terminal, a box with overline is not implemented, and is drawn as a box.
+            DecorationStyle::Box(style)
+            | DecorationStyle::BoxWithOverline(style)
+            | DecorationStyle::BoxWithUnderOverline(style) => writeln!(
+                writer,
+                "<span style=\"display:inline-block;border:{}\">{}</span>",
+                get_css_border(&style),
+                text
+            ),
+            DecorationStyle::BoxWithUnderline(style) => writeln!(
+                writer,
+                "<span style=\"display:inline-block;border-bottom:{border}{width}\">\
+                 <span style=\"display:inline-block;border:{border};border-bottom:none\">{text}</span>\
+                 </span>",
+                border = get_css_border(&style),
+                width = width,
+                text = text
+            ),
+            DecorationStyle::Underline(style) => writeln!(
+                writer,
+                "<span style=\"display:inline-block;border-bottom:{}{}\">{}</span>",
+                get_css_border(&style),
+                width,
+                text
+            ),
+            DecorationStyle::Overline(style) => writeln!(
+                writer,
+                "<span style=\"display:inline-block;border-top:{}{}\">{}</span>",
+                get_css_border(&style),
+                width,
+                text
+            ),
+            DecorationStyle::UnderOverline(style) => writeln!(
+                writer,
+                "<span style=\"display:inline-block;border-top:{border};border-bottom:{border}{width}\">\
+                 {text}</span>",
+                border = get_css_border(&style),
+                width = width,
+                text = text
+            ),
+            DecorationStyle::NoDecoration => writeln!(writer, "{}", text),
+        }
+    }
+}
+
+/// Convert text containing ANSI escape sequences to HTML. SGR sequences become spans, OSC 8
+/// hyperlinks become links, and all other escape sequences are discarded.
+fn ansi_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut style = ansi_term::Style::new();
+    let mut in_link = false;
+    let mut offset = 0;
+    for captures in ESCAPE_SEQUENCE_REGEX.captures_iter(text) {
+        let escape_sequence = captures.get(0).unwrap();
+        html.push_str(&paint(
+            &style,
+            &escape(&text[offset..escape_sequence.start()]),
+        ));
+        offset = escape_sequence.end();
+        if let Some(sgr) = captures.name("sgr") {
+            style = apply_sgr_parameters(style, sgr.as_str());
+        } else if let Some(url) = captures.name("url") {
+            if in_link {
+                html.push_str("</a>");
+            }
+            in_link = !url.as_str().is_empty();
+            if in_link {
+                html.push_str(&format!("<a href=\"{}\">", escape(url.as_str())));
+            }
+        }
+    }
+    html.push_str(&paint(&style, &escape(&text[offset..])));
+    if in_link {
+        html.push_str("</a>");
+    }
+    html
+}
+
+/// Return `html` wrapped in a span with the CSS equivalent of `style`.
+fn paint(style: &ansi_term::Style, html: &str) -> String {
+    let css = get_css(style);
+    if css.is_empty() || html.is_empty() {
+        html.to_string()
+    } else {
+        format!("<span style=\"{}\">{}</span>", css, html)
+    }
+}
+
+fn get_css(style: &ansi_term::Style) -> String {
+    let (foreground, background) = if style.is_reverse {
+        (
+            Some(
+                style
+                    .background
+                    .map_or("var(--background)".to_string(), get_css_color),
+            ),
+            Some(
+                style
+                    .foreground
+                    .map_or("var(--foreground)".to_string(), get_css_color),
+            ),
+        )
+    } else {
+        (
+            style.foreground.map(get_css_color),
+            style.background.map(get_css_color),
+        )
+    };
+    let mut declarations = Vec::new();
+    if let Some(color) = foreground {
+        declarations.push(format!("color:{}", color));
+    }
+    if let Some(color) = background {
+        declarations.push(format!("background-color:{}", color));
+    }
+    if style.is_bold {
+        declarations.push("font-weight:bold".to_string());
+    }
+    if style.is_dimmed {
+        declarations.push("opacity:0.5".to_string());
+    }
+    if style.is_italic {
+        declarations.push("font-style:italic".to_string());
+    }
+    match (style.is_underline, style.is_strikethrough) {
+        (true, true) => declarations.push("text-decoration:underline line-through".to_string()),
+        (true, false) => declarations.push("text-decoration:underline".to_string()),
+        (false, true) => declarations.push("text-decoration:line-through".to_string()),
+        (false, false) => {}
+    }
+    if style.is_hidden {
+        declarations.push("visibility:hidden".to_string());
+    }
+    declarations.join(";")
+}
+
+fn get_css_border(decoration_style: &ansi_term::Style) -> String {
+    format!(
+        "{}px solid {}",
+        if decoration_style.is_bold { 2 } else { 1 },
+        decoration_style
+            .foreground
+            .map_or("currentColor".to_string(), get_css_color)
+    )
+}
+
+fn get_css_color(color: Color) -> String {
+    let (r, g, b) = match color {
+        Color::RGB(r, g, b) => (r, g, b),
+        Color::Fixed(n) => ansi_colours::rgb_from_ansi256(n),
+        Color::Black => ansi_colours::rgb_from_ansi256(0),
+        Color::Red => ansi_colours::rgb_from_ansi256(1),
+        Color::Green => ansi_colours::rgb_from_ansi256(2),
+        Color::Yellow => ansi_colours::rgb_from_ansi256(3),
+        Color::Blue => ansi_colours::rgb_from_ansi256(4),
+        Color::Purple => ansi_colours::rgb_from_ansi256(5),
+        Color::Cyan => ansi_colours::rgb_from_ansi256(6),
+        Color::White => ansi_colours::rgb_from_ansi256(7),
+    };
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Return the style resulting from applying the parameters of an SGR escape sequence, e.g. "1;31"
+/// in "\x1b[1;31m", to `style`.
+fn apply_sgr_parameters(mut style: ansi_term::Style, parameters: &str) -> ansi_term::Style {
+    let parameters: Vec<u8> = parameters
+        .split(';')
+        .map(|parameter| parameter.parse().unwrap_or(0))
+        .collect();
+    let mut i = 0;
+    while i < parameters.len() {
+        match parameters[i] {
+            0 => style = ansi_term::Style::new(),
+            1 => style.is_bold = true,
+            2 => style.is_dimmed = true,
+            3 => style.is_italic = true,
+            4 => style.is_underline = true,
+            5 => style.is_blink = true,
+            7 => style.is_reverse = true,
+            8 => style.is_hidden = true,
+            9 => style.is_strikethrough = true,
+            22 => {
+                style.is_bold = false;
+                style.is_dimmed = false;
+            }
+            23 => style.is_italic = false,
+            24 => style.is_underline = false,
+            25 => style.is_blink = false,
+            27 => style.is_reverse = false,
+            28 => style.is_hidden = false,
+            29 => style.is_strikethrough = false,
+            n @ 30..=37 => style.foreground = Some(Color::Fixed(n - 30)),
+            n @ 40..=47 => style.background = Some(Color::Fixed(n - 40)),
+            n @ 90..=97 => style.foreground = Some(Color::Fixed(n - 90 + 8)),
+            n @ 100..=107 => style.background = Some(Color::Fixed(n - 100 + 8)),
+            39 => style.foreground = None,
+            49 => style.background = None,
+            n @ 38 | n @ 48 => {
+                let color = match parameters.get(i + 1) {
+                    Some(5) if i + 2 < parameters.len() => {
+                        i += 2;
+                        Some(Color::Fixed(parameters[i]))
+                    }
+                    Some(2) if i + 4 < parameters.len() => {
+                        i += 4;
+                        Some(Color::RGB(
+                            parameters[i - 2],
+                            parameters[i - 1],
+                            parameters[i],
+                        ))
+                    }
+                    _ => None,
+                };
+                if n == 38 {
+                    style.foreground = color;
+                } else {
+                    style.background = color;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    style
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use crate::cli;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    fn get_html(input: &str, options: cli::Opt) -> String {
+        let mut options = options;
+        options.output_format = "html".to_string();
+        let config = cli::process_command_line_arguments(options);
+        let mut writer: Vec<u8> = Vec::new();
+        write_html(
+            ByteLines::new(BufReader::new(input.as_bytes())),
+            &mut writer,
+            &config,
+        )
+        .unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn test_ansi_to_html() {
+        assert_eq!(ansi_to_html("a < b"), "a &lt; b");
+        assert_eq!(
+            ansi_to_html("\x1b[1;31mred\x1b[0m plain"),
+            "<span style=\"color:#ce0000;font-weight:bold\">red</span> plain"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[38;2;1;2;3;48;5;16mx\x1b[K"),
+            "<span style=\"color:#010203;background-color:#000000\">x</span>"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x1b\\"),
+            "<a href=\"file:///a.rs\">a.rs</a>"
+        );
+    }
+
+    #[test]
+    fn test_html_output_is_a_document_with_the_theme_background() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.theme = Some("GitHub".to_string());
+        let html = get_html(GIT_DIFF, options);
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("--background:#ffffff;"));
+        assert!(html.ends_with("</pre>\n</body>\n</html>\n"));
+        assert!(!html.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_html_output_has_spans_and_css_decorations() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.file_style = "blue".to_string();
+        options.file_decoration_style = "blue ul".to_string();
+        options.hunk_header_decoration_style = "blue box".to_string();
+        options.width = Some("40".to_string());
+        let html = get_html(GIT_DIFF, options);
+        assert!(html.contains(
+            "<span style=\"display:inline-block;border-bottom:1px solid #0000ee;min-width:40ch\">\
+             <span style=\"color:#0000ee\">src/main.rs</span></span>"
+        ));
+        assert!(html.contains("<span style=\"display:inline-block;border:1px solid #0000ee\">"));
+        assert!(html.contains("println!(&quot;Hello, "));
+        assert!(!html.contains('─') && !html.contains('│'));
+        // Added and removed lines are blocks with the background color of their style.
+        assert!(html.contains("<span style=\"display:block;background-color:"));
+    }
+
+    const GIT_DIFF: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 8e1e71d..f7fa5b8 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@ mod tests
+ fn main() {
+-    println!(\"Hello, world\");
++    println!(\"Hello, \\\"world\\\"\");
+ }
+";
+}
@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::config::Config;
+
+lazy_static! {
+    static ref OSC8_HYPERLINK_REGEX: Regex =
+        Regex::new(r"\x1b\]8;[^\x1b\x07]*;[^\x1b\x07]*(?:\x1b\\|\x07)").unwrap();
+    static ref COMMIT_LINE_REGEX: Regex =
+        Regex::new(r"^((?:\x1b\[[0-9;]*m)*commit (?:\x1b\[[0-9;]*m)*)([0-9a-f]{7,40})").unwrap();
+}
+
+/// Return `text` as an OSC 8 hyperlink to `url`.
+/// See https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feda
+pub fn format_osc8_hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Return `text` as a hyperlink to the file at `relative_path` (relative to the root of the git
+/// repository), and optionally to a line in that file, formatted according to the file link
+/// format. Without a line number, a `:{line}` suffix in the format is omitted.
+pub fn format_osc8_file_hyperlink(
+    relative_path: &str,
+    line_number: Option<usize>,
+    text: &str,
+    config: &Config,
+) -> String {
+    let absolute_path = get_absolute_path(relative_path, config);
+    let url = config
+        .hyperlinks_file_link_format
+        .replace("{path}", &absolute_path.to_string_lossy());
+    let url = match line_number {
+        Some(n) => url.replace("{line}", &n.to_string()),
+        None => url.replace(":{line}", "").replace("{line}", ""),
+    };
+    format_osc8_hyperlink(&url, text)
+}
+
+/// If a commit link format has been supplied, replace the commit hash in a commit metadata line
+/// (which may contain ANSI color escape sequences) with a hyperlink to the commit.
+pub fn format_commit_line_with_osc8_commit_hyperlink<'a>(
+    line: &'a str,
+    config: &Config,
+) -> Cow<'a, str> {
+    match &config.hyperlinks_commit_link_format {
+        Some(commit_link_format) => COMMIT_LINE_REGEX.replace(line, |captures: &Captures| {
+            let commit = captures.get(2).unwrap().as_str();
+            format!(
+                "{}{}",
+                captures.get(1).unwrap().as_str(),
+                format_osc8_hyperlink(&commit_link_format.replace("{commit}", commit), commit)
+            )
+        }),
+        None => Cow::from(line),
+    }
+}
+
+/// Remove OSC 8 hyperlink escape sequences, leaving the link text.
+pub fn strip_osc8_hyperlinks(text: &str) -> Cow<'_, str> {
+    OSC8_HYPERLINK_REGEX.replace_all(text, "")
+}
+
+fn get_absolute_path(path: &str, config: &Config) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match &config.git_root {
+        Some(git_root) => git_root.join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_strip_osc8_hyperlinks() {
+        let text = format!("a {} b", format_osc8_hyperlink("file:///x/y.rs", "y.rs"));
+        assert_eq!(strip_osc8_hyperlinks(&text), "a y.rs b");
+    }
+
+    #[test]
+    fn test_commit_hyperlink() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.hyperlinks = true;
+        options.hyperlinks_commit_link_format =
+            Some("https://git.example.com/cgit/delta/commit/?id={commit}".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_WITH_COMMIT, options);
+        assert!(output.contains(&format!(
+            "commit {}",
+            format_osc8_hyperlink(
+                "https://git.example.com/cgit/delta/commit/?id=94907c0f136f46dc46ffae2dc92dca9af7eb7c2e",
+                "94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"
+            )
+        )));
+    }
+
+    #[test]
+    fn test_file_and_hunk_hyperlinks() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.hyperlinks = true;
+        options.hyperlinks_file_link_format = "file-line://{path}:{line}".to_string();
+        let (output, config) = integration_test_utils::run_delta(GIT_DIFF_WITH_COMMIT, options);
+        let absolute_path = get_absolute_path("src/main.rs", &config);
+        let absolute_path = absolute_path.to_string_lossy();
+        assert!(output.contains(&format_osc8_hyperlink(
+            &format!("file-line://{}", absolute_path),
+            "src/main.rs"
+        )));
+        assert!(output.contains(&format_osc8_hyperlink(
+            &format!("file-line://{}:2", absolute_path),
+            "2"
+        )));
+        // The decorations are drawn as if the hyperlinks were not there.
+        let output = strip_osc8_hyperlinks(&output);
+        assert!(strip_ansi_codes(&output).contains("\nsrc/main.rs\n───────────"));
+    }
+
+    #[test]
+    fn test_hunk_hyperlink_of_deleted_file_links_to_minus_file() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.hyperlinks = true;
+        options.hyperlinks_file_link_format = "file-line://{path}:{line}".to_string();
+        let (output, config) = integration_test_utils::run_delta(GIT_DIFF_DELETED_FILE, options);
+        let absolute_path = get_absolute_path("src/old.rs", &config);
+        assert!(output.contains(&format_osc8_hyperlink(
+            &format!("file-line://{}:1", absolute_path.to_string_lossy()),
+            "0"
+        )));
+        assert!(!output.contains("/dev/null:"));
+    }
+
+    const GIT_DIFF_WITH_COMMIT: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed May 27 13:35:16 2020 -0400
+
+    Add module
+
+diff --git a/src/main.rs b/src/main.rs
+index 9f2b9d4..bbf1e9d 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -2,3 +2,4 @@
+ mod align;
++mod hyperlinks;
+ mod bat;
+ mod cli;
+";
+
+    const GIT_DIFF_DELETED_FILE: &str = "\
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+index 9f2b9d4..0000000
+--- a/src/old.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-mod align;
+-mod bat;
+";
+}
@@ -0,0 +1,621 @@
+use std::io::{BufRead, Write};
+
+use bytelines::ByteLines;
+use git_delta::events::{DiffEvent, DiffEventParser};
+use git_delta::parse::State;
+use serde_json::{json, Value};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+
+use crate::config::Config;
+use crate::edits;
+use crate::paint::Painter;
+use crate::word_diff;
+
+/// The diff, as parsed for JSON output. Files that are not preceded by commit metadata (e.g. the
+/// output of `git diff`) are held at the top level; the files of a commit are held by the commit.
+#[derive(Default)]
+struct Diff {
+    commits: Vec<Commit>,
+    files: Vec<File>,
+}
+
+#[derive(Default)]
+struct Commit {
+    hash: String,
+    author: Option<String>,
+    date: Option<String>,
+    message_lines: Vec<String>,
+    meta: Vec<String>,
+    files: Vec<File>,
+}
+
+#[derive(Default)]
+struct File {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    is_rename: bool,
+    is_binary: bool,
+    old_mode: Option<String>,
+    new_mode: Option<String>,
+    meta: Vec<String>,
+    hunks: Vec<Hunk>,
+}
+
+struct Hunk {
+    header: String,
+    function_context: String,
+    line_number_ranges: Vec<(usize, usize)>,
+    lines: Vec<Line>,
+}
+
+/// The sections of a line, as (is_emph, text) pairs.
+type Sections<'a> = Vec<(bool, &'a str)>;
+
+struct Line {
+    state: State,
+    prefix: String,
+    content: String,
+    no_newline_at_end_of_file: bool,
+}
+
+/// Parse the diff and write it to `writer` as a single JSON document describing its commits,
+/// files, hunks and lines. Each removed or added line is annotated with its homologous line (if
+/// any) and its emph sections, as inferred for display, and optionally with the syntax scopes of
+/// its sections.
+pub fn write_json<I>(
+    lines: ByteLines<I>,
+    writer: &mut dyn Write,
+    config: &Config,
+) -> std::io::Result<()>
+where
+    I: BufRead,
+{
+    let mut diff = Diff::default();
+    // The metadata lines of the next file, which is created by its FileHeader event.
+    let mut file_meta_lines = Vec::new();
+    // Whether the last event was the FileHeader event of the current file, or a metadata line
+    // following it (e.g. "Binary files ... differ").
+    let mut is_after_file_header = false;
+    for event in DiffEventParser::from_byte_lines(lines) {
+        let event = event?;
+        is_after_file_header = match event {
+            DiffEvent::FileHeader { .. } => true,
+            DiffEvent::FileMeta { .. } => is_after_file_header,
+            _ => false,
+        };
+        match event {
+            DiffEvent::CommitHeader { hash, line } => diff.commits.push(Commit {
+                hash,
+                meta: vec![line],
+                ..Commit::default()
+            }),
+            DiffEvent::CommitMeta { line } => {
+                if let Some(commit) = diff.commits.last_mut() {
+                    handle_commit_meta_line(commit, line);
+                }
+            }
+            DiffEvent::FileMeta { line } => match diff.current_files().last_mut() {
+                Some(file) if is_after_file_header => {
+                    handle_file_meta_line(file, &line);
+                    file.meta.push(line);
+                }
+                _ => file_meta_lines.push(line),
+            },
+            DiffEvent::FileHeader {
+                minus_file,
+                plus_file,
+            } => {
+                let mut file = File {
+                    old_path: get_path(minus_file),
+                    new_path: get_path(plus_file),
+                    ..File::default()
+                };
+                for line in file_meta_lines.drain(..) {
+                    handle_file_meta_line(&mut file, &line);
+                    file.meta.push(line);
+                }
+                diff.current_files().push(file);
+            }
+            DiffEvent::HunkHeader {
+                line,
+                code_fragment,
+                line_numbers,
+            } => {
+                if let Some(file) = diff.current_files().last_mut() {
+                    file.hunks.push(Hunk {
+                        header: line,
+                        function_context: code_fragment.trim().to_string(),
+                        line_number_ranges: line_numbers,
+                        lines: Vec::new(),
+                    });
+                }
+            }
+            DiffEvent::MinusLine { prefix, content } => {
+                diff.push_line(State::HunkMinus, prefix, content)
+            }
+            DiffEvent::PlusLine { prefix, content } => {
+                diff.push_line(State::HunkPlus, prefix, content)
+            }
+            DiffEvent::ZeroLine { prefix, content } => {
+                diff.push_line(State::HunkZero, prefix, content)
+            }
+            DiffEvent::WordDiffLine { tokens } => {
+                if word_diff::is_unchanged(&tokens) {
+                    let content = tokens.into_iter().map(|(_, text)| text).collect();
+                    diff.push_line(State::HunkZero, " ".to_string(), content);
+                } else {
+                    for (kind, state) in &[('-', State::HunkMinus), ('+', State::HunkPlus)] {
+                        if let Some((content, _)) =
+                            word_diff::get_line_and_emph_ranges(&tokens, *kind)
+                        {
+                            diff.push_line(state.clone(), kind.to_string(), content);
+                        }
+                    }
+                }
+            }
+            DiffEvent::Other { line } => {
+                // E.g. "\ No newline at end of file", which refers to the preceding line.
+                if line.starts_with('\\') {
+                    if let Some(last_line) =
+                        diff.current_hunk().and_then(|hunk| hunk.lines.last_mut())
+                    {
+                        last_line.no_newline_at_end_of_file = true;
+                    }
+                }
+            }
+        }
+    }
+    serde_json::to_writer_pretty(&mut *writer, &diff.to_json(config))?;
+    writeln!(writer)
+}
+
+fn handle_commit_meta_line(commit: &mut Commit, line: String) {
+    if let Some(author) = line.strip_prefix("Author: ") {
+        commit.author = Some(author.trim().to_string());
+    } else if let Some(date) = line.strip_prefix("Date: ") {
+        commit.date = Some(date.trim().to_string());
+    } else if let Some(message_line) = line.strip_prefix("    ") {
+        commit.message_lines.push(message_line.to_string());
+    }
+    commit.meta.push(line);
+}
+
+fn handle_file_meta_line(file: &mut File, line: &str) {
+    if line.starts_with("rename from ") || line.starts_with("rename to ") {
+        file.is_rename = true;
+    } else if let Some(mode) = line.strip_prefix("old mode ") {
+        file.old_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("new mode ") {
+        file.new_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("new file mode ") {
+        file.old_path = None;
+        file.new_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+        file.new_path = None;
+        file.old_mode = Some(mode.to_string());
+    } else if line.starts_with("Binary files ") {
+        file.is_binary = true;
+    }
+}
+
+/// Return the path of a FileHeader event, or None for /dev/null.
+fn get_path(path: String) -> Option<String> {
+    match path {
+        path if path == "/dev/null" => None,
+        path => Some(path),
+    }
+}
+
+impl Diff {
+    /// The hunk currently being parsed, if any.
+    fn current_hunk(&mut self) -> Option<&mut Hunk> {
+        self.current_files()
+            .last_mut()
+            .and_then(|file| file.hunks.last_mut())
+    }
+
+    /// Add a line to the hunk currently being parsed, if any.
+    fn push_line(&mut self, state: State, prefix: String, content: String) {
+        if let Some(hunk) = self.current_hunk() {
+            hunk.lines.push(Line {
+                state,
+                prefix,
+                content,
+                no_newline_at_end_of_file: false,
+            });
+        }
+    }
+
+    /// The list of files to which a new file should be added.
+    fn current_files(&mut self) -> &mut Vec<File> {
+        match self.commits.last_mut() {
+            Some(commit) => &mut commit.files,
+            None => &mut self.files,
+        }
+    }
+
+    fn to_json(&self, config: &Config) -> Value {
+        json!({
+            "commits": self.commits.iter().map(|commit| commit.to_json(config)).collect::<Vec<_>>(),
+            "files": self.files.iter().map(|file| file.to_json(config)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Commit {
+    fn to_json(&self, config: &Config) -> Value {
+        json!({
+            "hash": self.hash,
+            "author": self.author,
+            "date": self.date,
+            "message": self.message_lines.join("\n").trim(),
+            "meta": self.meta,
+            "files": self.files.iter().map(|file| file.to_json(config)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl File {
+    fn to_json(&self, config: &Config) -> Value {
+        let path = self.new_path.as_ref().or(self.old_path.as_ref());
+        let syntax = Painter::get_syntax(config, path.map(String::as_str));
+        json!({
+            "old_path": self.old_path,
+            "new_path": self.new_path,
+            "is_rename": self.is_rename,
+            "is_binary": self.is_binary,
+            "old_mode": self.old_mode,
+            "new_mode": self.new_mode,
+            "meta": self.meta,
+            "hunks": self.hunks.iter().map(|hunk| {
+                let mut parse_state = ParseState::new(syntax);
+                hunk.to_json(&mut parse_state, config)
+            }).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Hunk {
+    fn to_json(&self, parse_state: &mut ParseState, config: &Config) -> Value {
+        let (homologs, sections) = self.get_homologs_and_emph_sections(config);
+        let line_numbers = self.get_line_numbers();
+        let mut scope_stack = ScopeStack::new();
+        let lines: Vec<Value> = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut value = json!({
+                    "type": match line.state {
+                        State::HunkMinus => "minus",
+                        State::HunkPlus => "plus",
+                        _ => "zero",
+                    },
+                    "prefix": line.prefix,
+                    "content": line.content,
+                    "old_line_number": line_numbers[i].0,
+                    "new_line_number": line_numbers[i].1,
+                    "homolog": homologs[i],
+                    "sections": sections[i]
+                        .iter()
+                        .map(|(is_emph, text)| json!({"text": text, "emph": is_emph}))
+                        .collect::<Vec<_>>(),
+                    "no_newline_at_end_of_file": line.no_newline_at_end_of_file,
+                });
+                if config.json_syntax_scopes {
+                    value["syntax_scopes"] = get_syntax_scope_sections(
+                        &line.content,
+                        parse_state,
+                        &mut scope_stack,
+                        &config.syntax_set,
+                    );
+                }
+                value
+            })
+            .collect();
+        json!({
+            "header": self.header,
+            "function_context": self.function_context,
+            "line_number_ranges": self
+                .line_number_ranges
+                .iter()
+                .map(|(start, count)| json!({"start": start, "count": count}))
+                .collect::<Vec<_>>(),
+            "lines": lines,
+        })
+    }
+
+    /// Return the (minus, plus) line numbers of each line. A combined diff has a minus line number
+    /// range for each parent; the line numbers given for it are those of the first parent.
+    fn get_line_numbers(&self) -> Vec<(Option<usize>, Option<usize>)> {
+        let (mut minus_line_number, _) = self.line_number_ranges.first().cloned().unwrap_or((0, 0));
+        let (mut plus_line_number, _) = self.line_number_ranges.last().cloned().unwrap_or((0, 0));
+        self.lines
+            .iter()
+            .map(|line| {
+                let has_minus_line_number = line.state != State::HunkPlus;
+                let has_plus_line_number = line.state != State::HunkMinus;
+                let line_numbers = (
+                    if has_minus_line_number {
+                        Some(minus_line_number)
+                    } else {
+                        None
+                    },
+                    if has_plus_line_number {
+                        Some(plus_line_number)
+                    } else {
+                        None
+                    },
+                );
+                minus_line_number += has_minus_line_number as usize;
+                plus_line_number += has_plus_line_number as usize;
+                line_numbers
+            })
+            .collect()
+    }
+
+    /// Pair homologous minus and plus lines and infer their emph sections, as is done for display:
+    /// each run of minus lines is considered together with the run of plus lines following it.
+    /// Return, for each line, the index of its homologous line (if any) and its sections as
+    /// (is_emph, text) pairs.
+    fn get_homologs_and_emph_sections(
+        &self,
+        config: &Config,
+    ) -> (Vec<Option<usize>>, Vec<Sections<'_>>) {
+        let mut homologs = vec![None; self.lines.len()];
+        let mut sections: Vec<Sections> = self
+            .lines
+            .iter()
+            .map(|line| vec![(false, line.content.as_str())])
+            .collect();
+        let mut i = 0;
+        while i < self.lines.len() {
+            let minus_start = i;
+            while i < self.lines.len() && self.lines[i].state == State::HunkMinus {
+                i += 1;
+            }
+            let plus_start = i;
+            while i < self.lines.len() && self.lines[i].state == State::HunkPlus {
+                i += 1;
+            }
+            if minus_start == i {
+                i += 1; // a zero line
+                continue;
+            }
+            let minus_lines: Vec<String> = self.lines[minus_start..plus_start]
+                .iter()
+                .map(|line| line.content.clone())
+                .collect();
+            let plus_lines: Vec<String> = self.lines[plus_start..i]
+                .iter()
+                .map(|line| line.content.clone())
+                .collect();
+            let (annotated_minus_lines, annotated_plus_lines, line_alignment) = edits::infer_edits(
+                &minus_lines,
+                &plus_lines,
+                false,
+                true,
+                false,
+                true,
+                config.max_line_distance,
+                config.max_line_distance_for_naively_paired_lines,
+            );
+            for (j, annotated_line) in annotated_minus_lines.iter().enumerate() {
+                sections[minus_start + j] =
+                    get_line_sections(&self.lines[minus_start + j].content, annotated_line);
+            }
+            for (j, annotated_line) in annotated_plus_lines.iter().enumerate() {
+                sections[plus_start + j] =
+                    get_line_sections(&self.lines[plus_start + j].content, annotated_line);
+            }
+            for (minus_index, plus_index) in line_alignment {
+                if let (Some(minus_index), Some(plus_index)) = (minus_index, plus_index) {
+                    homologs[minus_start + minus_index] = Some(plus_start + plus_index);
+                    homologs[plus_start + plus_index] = Some(minus_start + minus_index);
+                }
+            }
+        }
+        (homologs, sections)
+    }
+}
+
+/// Coalesce the annotated sections of a line (which refer to a copy of the line) into sections of
+/// the line itself.
+fn get_line_sections<'a>(line: &'a str, annotated_line: &[(bool, &str)]) -> Vec<(bool, &'a str)> {
+    let mut sections: Vec<(bool, &str)> = Vec::new();
+    let mut offset = 0;
+    for (is_emph, text) in annotated_line {
+        let end = offset + text.len();
+        match sections.last_mut() {
+            Some((last_is_emph, last_text)) if last_is_emph == is_emph => {
+                *last_text = &line[offset - last_text.len()..end];
+            }
+            _ => sections.push((*is_emph, &line[offset..end])),
+        }
+        offset = end;
+    }
+    sections
+}
+
+/// Return the sections of the line, each with the space-separated stack of syntax scopes that
+/// applies to it.
+fn get_syntax_scope_sections(
+    line: &str,
+    parse_state: &mut ParseState,
+    scope_stack: &mut ScopeStack,
+    syntax_set: &SyntaxSet,
+) -> Value {
+    // Many syntax definitions require the terminating newline.
+    let line = format!("{}\n", line);
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    for (i, op) in parse_state.parse_line(&line, syntax_set) {
+        if i > offset {
+            sections.push(get_syntax_scope_section(&line[offset..i], scope_stack));
+            offset = i;
+        }
+        scope_stack.apply(&op);
+    }
+    let end = line.len() - 1;
+    if end > offset {
+        sections.push(get_syntax_scope_section(&line[offset..end], scope_stack));
+    }
+    Value::Array(sections)
+}
+
+fn get_syntax_scope_section(text: &str, scope_stack: &ScopeStack) -> Value {
+    let scopes: Vec<String> = scope_stack
+        .as_slice()
+        .iter()
+        .map(|scope| scope.build_string())
+        .collect();
+    json!({"text": text, "scopes": scopes.join(" ")})
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    fn get_json(input: &str, json_syntax_scopes: bool) -> Value {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.output_format = "json".to_string();
+        options.json_syntax_scopes = json_syntax_scopes;
+        let config = cli::process_command_line_arguments(options);
+        let mut writer = Vec::new();
+        write_json(
+            ByteLines::new(std::io::BufReader::new(input.as_bytes())),
+            &mut writer,
+            &config,
+        )
+        .unwrap();
+        serde_json::from_slice(&writer).unwrap()
+    }
+
+    #[test]
+    fn test_commit_and_file_metadata() {
+        let value = get_json(GIT_SHOW_WITH_RENAME, false);
+        assert_eq!(value["files"], json!([]));
+        let commit = &value["commits"][0];
+        assert_eq!(commit["hash"], "94907c0f136f46dc46ffae2dc92dca9af7eb7c2e");
+        assert_eq!(commit["author"], "Dan Davison <dandavison7@gmail.com>");
+        assert_eq!(commit["message"], "Rename and edit module");
+        let file = &commit["files"][0];
+        assert_eq!(file["old_path"], "src/old.rs");
+        assert_eq!(file["new_path"], "src/new.rs");
+        assert_eq!(file["is_rename"], true);
+        assert_eq!(file["old_mode"], "100644");
+        assert_eq!(file["new_mode"], "100755");
+        let hunk = &file["hunks"][0];
+        assert_eq!(hunk["function_context"], "fn main() {");
+        assert_eq!(
+            hunk["line_number_ranges"],
+            json!([{"start": 10, "count": 3}, {"start": 10, "count": 3}])
+        );
+    }
+
+    #[test]
+    fn test_line_pairing_and_emph_sections() {
+        let value = get_json(GIT_SHOW_WITH_RENAME, false);
+        let lines = &value["commits"][0]["files"][0]["hunks"][0]["lines"];
+        assert_eq!(lines[0]["type"], "zero");
+        assert_eq!(lines[0]["old_line_number"], 10);
+        assert_eq!(lines[0]["new_line_number"], 10);
+        assert_eq!(lines[1]["type"], "minus");
+        assert_eq!(lines[1]["content"], "    let x = 1;");
+        assert_eq!(lines[1]["old_line_number"], 11);
+        assert_eq!(lines[1]["new_line_number"], Value::Null);
+        assert_eq!(lines[1]["homolog"], 2);
+        assert_eq!(lines[2]["type"], "plus");
+        assert_eq!(lines[2]["homolog"], 1);
+        assert_eq!(lines[2]["new_line_number"], 11);
+        assert_eq!(
+            lines[2]["sections"],
+            json!([
+                {"text": "    let x = ", "emph": false},
+                {"text": "2", "emph": true},
+                {"text": ";", "emph": false},
+            ])
+        );
+        assert_eq!(lines[3]["old_line_number"], 12);
+        assert_eq!(lines[3]["new_line_number"], 12);
+    }
+
+    #[test]
+    fn test_syntax_scopes() {
+        let value = get_json(GIT_SHOW_WITH_RENAME, true);
+        let lines = &value["commits"][0]["files"][0]["hunks"][0]["lines"];
+        let scope_sections = lines[2]["syntax_scopes"].as_array().unwrap();
+        let text: String = scope_sections
+            .iter()
+            .map(|section| section["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(text, "    let x = 2;");
+        assert!(scope_sections.iter().any(|section| section["text"] == "let"
+            && section["scopes"]
+                .as_str()
+                .unwrap()
+                .contains("storage.type.rust")));
+        assert_eq!(
+            get_json(GIT_SHOW_WITH_RENAME, false)["commits"][0]["files"][0]["hunks"][0]["lines"][2]
+                ["syntax_scopes"],
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_added_and_binary_files() {
+        let value = get_json(GIT_DIFF_WITH_ADDED_AND_BINARY_FILES, false);
+        let files = &value["files"];
+        assert_eq!(files[0]["old_path"], Value::Null);
+        assert_eq!(files[0]["new_path"], "a.txt");
+        assert_eq!(files[0]["new_mode"], "100644");
+        let lines = &files[0]["hunks"][0]["lines"];
+        assert_eq!(lines[0]["content"], "a");
+        assert_eq!(lines[0]["no_newline_at_end_of_file"], true);
+        assert_eq!(files[1]["old_path"], Value::Null);
+        assert_eq!(files[1]["new_path"], "b.png");
+        assert_eq!(files[1]["is_binary"], true);
+        assert_eq!(files[1]["hunks"], json!([]));
+    }
+
+    const GIT_DIFF_WITH_ADDED_AND_BINARY_FILES: &str = "\
+diff --git a/a.txt b/a.txt
+new file mode 100644
+index 0000000..2e65efe
+--- /dev/null
++++ b/a.txt
+@@ -0,0 +1 @@
++a
+\\ No newline at end of file
+diff --git a/b.png b/b.png
+new file mode 100644
+index 0000000..e2a5b4c
+Binary files /dev/null and b/b.png differ
+";
+
+    const GIT_SHOW_WITH_RENAME: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed May 27 13:35:16 2020 -0400
+
+    Rename and edit module
+
+diff --git a/src/old.rs b/src/new.rs
+old mode 100644
+new mode 100755
+similarity index 90%
+rename from src/old.rs
+rename to src/new.rs
+index 9f2b9d4..bbf1e9d
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -10,3 +10,3 @@ fn main() {
+ fn f() {
+-    let x = 1;
++    let x = 2;
+ }
+";
+}
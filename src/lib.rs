@@ -0,0 +1,8 @@
+//! Delta's diff parser, for programs that display diffs themselves.
+//!
+//! [`events::DiffEventParser`] parses the output of `git diff`, `git show`, `git log -p`, or
+//! `diff -u` into a stream of typed events, independently of how the `delta` binary paints them.
+//! [`parse`] holds the functions that it uses to parse individual lines.
+
+pub mod events;
+pub mod parse;
@@ -0,0 +1,254 @@
+use std::cmp::max;
+
+use git_delta::parse::State;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config;
+use crate::side_by_side::PanelSide;
+use crate::style::Style;
+
+/// The state needed to display line numbers in a gutter alongside the lines of a hunk.
+pub struct LineNumbersData<'a> {
+    pub left_format_data: Vec<LineNumberFormatSection<'a>>,
+    pub right_format_data: Vec<LineNumberFormatSection<'a>>,
+    pub line_number: (usize, usize), // (minus, plus) line numbers of the next line in the hunk
+    pub hunk_max_line_number_width: usize,
+}
+
+/// A format string such as "{nm:^4}⋮" is parsed into a sequence of sections, each of which is
+/// either literal text or a line number placeholder.
+#[derive(Debug, PartialEq)]
+pub enum LineNumberFormatSection<'a> {
+    Text(&'a str),
+    Placeholder {
+        name: &'a str,
+        alignment: Option<&'a str>,
+        width: Option<usize>,
+    },
+}
+
+lazy_static! {
+    static ref LINE_NUMBERS_PLACEHOLDER_REGEX: Regex =
+        Regex::new(r"\{(nm|np)(?::([<^>])?([0-9]+)?)?\}").unwrap();
+}
+
+impl<'a> LineNumbersData<'a> {
+    pub fn from_format_strings(left_format: &'a str, right_format: &'a str) -> Self {
+        Self {
+            left_format_data: parse_line_number_format(left_format),
+            right_format_data: parse_line_number_format(right_format),
+            line_number: (0, 0),
+            hunk_max_line_number_width: 0,
+        }
+    }
+
+    /// Reset the line counters to the start of a hunk, given the (start, count) line number ranges
+    /// from its header. The first range is that of the minus file and the last that of the plus
+    /// file.
+    pub fn initialize_hunk(&mut self, line_number_ranges: &[(usize, usize)]) {
+        let (minus_start, minus_count) = line_number_ranges.first().cloned().unwrap_or((0, 0));
+        let (plus_start, plus_count) = line_number_ranges.last().cloned().unwrap_or((0, 0));
+        self.line_number = (minus_start, plus_start);
+        let hunk_max_line_number = max(minus_start + minus_count, plus_start + plus_count);
+        self.hunk_max_line_number_width = hunk_max_line_number.saturating_sub(1).to_string().len();
+    }
+}
+
+/// Return the painted line number gutter for a line in the given state, and advance the line
+/// counters accordingly. In side-by-side mode, the left panel displays the left format with the
+/// minus line number only, and the right panel the right format with the plus line number only.
+pub fn format_and_paint_line_numbers(
+    line_numbers_data: &mut LineNumbersData,
+    state: &State,
+    panel_side: Option<PanelSide>,
+    config: &config::Config,
+) -> Vec<ansi_term::ANSIString<'static>> {
+    let (minus_line_number, plus_line_number) = line_numbers_data.line_number;
+    let (has_minus_line_number, has_plus_line_number, number_style) = match state {
+        State::HunkMinus => (true, false, config.line_numbers_minus_style),
+        State::HunkPlus => (false, true, config.line_numbers_plus_style),
+        _ => (true, true, config.line_numbers_zero_style),
+    };
+    let minus_line_number = if has_minus_line_number && panel_side != Some(PanelSide::Right) {
+        line_numbers_data.line_number.0 += 1;
+        Some(minus_line_number)
+    } else {
+        None
+    };
+    let plus_line_number = if has_plus_line_number && panel_side != Some(PanelSide::Left) {
+        line_numbers_data.line_number.1 += 1;
+        Some(plus_line_number)
+    } else {
+        None
+    };
+
+    let mut ansi_strings = Vec::new();
+    if panel_side != Some(PanelSide::Right) {
+        ansi_strings.extend(format_and_paint_line_number_sections(
+            &line_numbers_data.left_format_data,
+            minus_line_number,
+            plus_line_number,
+            line_numbers_data.hunk_max_line_number_width,
+            number_style,
+            config.line_numbers_left_style,
+        ));
+    }
+    if panel_side != Some(PanelSide::Left) {
+        ansi_strings.extend(format_and_paint_line_number_sections(
+            &line_numbers_data.right_format_data,
+            minus_line_number,
+            plus_line_number,
+            line_numbers_data.hunk_max_line_number_width,
+            number_style,
+            config.line_numbers_right_style,
+        ));
+    }
+    ansi_strings
+}
+
+fn parse_line_number_format(format_string: &str) -> Vec<LineNumberFormatSection<'_>> {
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    for captures in LINE_NUMBERS_PLACEHOLDER_REGEX.captures_iter(format_string) {
+        let placeholder = captures.get(0).unwrap();
+        if placeholder.start() > offset {
+            sections.push(LineNumberFormatSection::Text(
+                &format_string[offset..placeholder.start()],
+            ));
+        }
+        sections.push(LineNumberFormatSection::Placeholder {
+            name: captures.get(1).unwrap().as_str(),
+            alignment: captures.get(2).map(|m| m.as_str()),
+            width: captures.get(3).and_then(|m| m.as_str().parse().ok()),
+        });
+        offset = placeholder.end();
+    }
+    if offset < format_string.len() {
+        sections.push(LineNumberFormatSection::Text(&format_string[offset..]));
+    }
+    sections
+}
+
+fn format_and_paint_line_number_sections(
+    format_data: &[LineNumberFormatSection],
+    minus_line_number: Option<usize>,
+    plus_line_number: Option<usize>,
+    hunk_max_line_number_width: usize,
+    number_style: Style,
+    text_style: Style,
+) -> Vec<ansi_term::ANSIString<'static>> {
+    format_data
+        .iter()
+        .map(|section| match section {
+            LineNumberFormatSection::Text(text) => {
+                text_style.ansi_term_style.paint(text.to_string())
+            }
+            LineNumberFormatSection::Placeholder {
+                name,
+                alignment,
+                width,
+            } => {
+                let line_number = if *name == "nm" {
+                    minus_line_number
+                } else {
+                    plus_line_number
+                };
+                number_style.ansi_term_style.paint(format_line_number(
+                    line_number,
+                    alignment.unwrap_or(">"),
+                    max(width.unwrap_or(0), hunk_max_line_number_width),
+                ))
+            }
+        })
+        .collect()
+}
+
+fn format_line_number(line_number: Option<usize>, alignment: &str, width: usize) -> String {
+    let n = line_number.map(|n| n.to_string()).unwrap_or_default();
+    match alignment {
+        "<" => format!("{0:<1$}", n, width),
+        "^" => format!("{0:^1$}", n, width),
+        _ => format!("{0:>1$}", n, width),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_line_number_format() {
+        assert_eq!(
+            parse_line_number_format("{nm:^4}⋮"),
+            vec![
+                LineNumberFormatSection::Placeholder {
+                    name: "nm",
+                    alignment: Some("^"),
+                    width: Some(4),
+                },
+                LineNumberFormatSection::Text("⋮"),
+            ]
+        );
+        assert_eq!(
+            parse_line_number_format("[{np}] {xx}"),
+            vec![
+                LineNumberFormatSection::Text("["),
+                LineNumberFormatSection::Placeholder {
+                    name: "np",
+                    alignment: None,
+                    width: None,
+                },
+                LineNumberFormatSection::Text("] {xx}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_line_number() {
+        assert_eq!(format_line_number(Some(7), ">", 3), "  7");
+        assert_eq!(format_line_number(Some(7), "<", 3), "7  ");
+        assert_eq!(format_line_number(Some(7), "^", 3), " 7 ");
+        assert_eq!(format_line_number(None, "^", 3), "   ");
+        assert_eq!(format_line_number(Some(1234), ">", 3), "1234");
+    }
+
+    #[test]
+    fn test_line_numbers_track_minus_plus_and_zero_lines() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.line_numbers = true;
+        options.line_numbers_left_format = "{nm:>2}|".to_string();
+        options.line_numbers_right_format = "{np:>2}|".to_string();
+        let (output, _) = integration_test_utils::run_delta(TWO_HUNKS_DIFF, options);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&" 1|  | fn print_square(num: f64) {"));
+        assert!(lines.contains(&"  | 1| fn print_cube(num: f64) {"));
+        assert!(lines.contains(&" 2| 2|     let result = f64::powf(num, 2.0);"));
+        assert!(lines.contains(&"  | 3|     // Added comment."));
+        assert!(lines.contains(&" 3| 4| }"));
+        // The width of the line numbers adjusts to the largest line number in the hunk.
+        assert!(lines.contains(&" 99|100| fn main() {"));
+        assert!(lines.contains(&"100|   | }"));
+    }
+
+    const TWO_HUNKS_DIFF: &str = "\
+diff --git a/example.rs b/example.rs
+index f38589a..0f1bb83 100644
+--- a/example.rs
++++ b/example.rs
+@@ -1,3 +1,4 @@
+-fn print_square(num: f64) {
++fn print_cube(num: f64) {
+     let result = f64::powf(num, 2.0);
++    // Added comment.
+ }
+@@ -99,2 +100,1 @@
+ fn main() {
+-}
+";
+}
@@ -7,18 +7,31 @@ mod align;
 mod bat;
 mod cli;
 mod color;
+mod color_moved;
 mod config;
 mod delta;
 mod draw;
 mod edits;
 mod env;
+mod features;
+mod git;
+mod git_config;
+mod html;
+mod hyperlinks;
+mod json_output;
+mod line_numbers;
 mod paint;
-mod parse;
+mod render;
 mod rewrite;
+mod side_by_side;
 mod style;
+mod syntax_mapping;
 mod syntect_color;
+mod terminal_background;
 mod tests;
 mod theme;
+mod whitespace_errors;
+mod word_diff;
 
 use std::io::{self, ErrorKind, Read, Write};
 use std::process;
@@ -26,11 +39,14 @@ use std::process;
 use ansi_term::{self, Color};
 use atty;
 use bytelines::ByteLinesReader;
-use structopt::StructOpt;
 
-use crate::bat::assets::{list_languages, HighlightingAssets};
+use crate::bat::assets::{clear_cache as clear_cache_dir, list_languages, HighlightingAssets};
+use crate::bat::dirs::DELTA_PROJECT_DIRS;
 use crate::bat::output::{OutputType, PagingMode};
+use crate::config::{ColorDepth, OutputFormat, Width};
 use crate::delta::delta;
+use crate::git_config::GitConfig;
+use crate::style::Style;
 
 mod errors {
     error_chain! {
@@ -43,7 +59,20 @@ mod errors {
 }
 
 fn main() -> std::io::Result<()> {
-    let opt = cli::Opt::from_args();
+    let git_config = GitConfig::try_create();
+    let opt = cli::Opt::from_args_and_git_config(git_config.as_ref());
+
+    if let Some(cli::Command::Cache(command)) = &opt.command {
+        let result = match command {
+            cli::CacheCommand::Build => build_cache(),
+            cli::CacheCommand::Clear => clear_cache(),
+        };
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        process::exit(0);
+    }
 
     if opt.list_languages {
         list_languages()?;
@@ -52,11 +81,12 @@ fn main() -> std::io::Result<()> {
         list_theme_names()?;
         process::exit(0);
     } else if opt.list_themes {
-        list_themes()?;
+        list_themes(&opt)?;
         process::exit(0);
     }
 
     let show_background_colors_option = opt.show_background_colors;
+    let show_config_option = opt.show_config;
 
     let config = cli::process_command_line_arguments(opt);
 
@@ -64,11 +94,26 @@ fn main() -> std::io::Result<()> {
         show_background_colors(&config);
         process::exit(0);
     }
+    if show_config_option {
+        show_config(&config);
+        process::exit(0);
+    }
 
     let mut output_type = OutputType::from_mode(config.paging_mode, None).unwrap();
     let mut writer = output_type.handle().unwrap();
 
-    if let Err(error) = delta(io::stdin().lock().byte_lines(), &mut writer, &config) {
+    let result = match config.output_format {
+        OutputFormat::Ansi | OutputFormat::Plain => {
+            delta(io::stdin().lock().byte_lines(), &mut writer, &config)
+        }
+        OutputFormat::Html => {
+            html::write_html(io::stdin().lock().byte_lines(), &mut writer, &config)
+        }
+        OutputFormat::Json => {
+            json_output::write_json(io::stdin().lock().byte_lines(), &mut writer, &config)
+        }
+    };
+    if let Err(error) = result {
         match error.kind() {
             ErrorKind::BrokenPipe => process::exit(0),
             _ => eprintln!("{}", error),
@@ -77,6 +122,25 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
+fn build_cache() -> crate::errors::Result<()> {
+    let source_dir = DELTA_PROJECT_DIRS.config_dir();
+    let cache_dir = DELTA_PROJECT_DIRS.cache_dir();
+    println!(
+        "Compiling syntax definitions and themes from {}",
+        source_dir.to_string_lossy()
+    );
+    HighlightingAssets::from_files(source_dir)?.save_to_cache(cache_dir)?;
+    println!("Wrote cache to {}", cache_dir.to_string_lossy());
+    Ok(())
+}
+
+fn clear_cache() -> crate::errors::Result<()> {
+    let cache_dir = DELTA_PROJECT_DIRS.cache_dir();
+    clear_cache_dir(cache_dir)?;
+    println!("Cleared cache in {}", cache_dir.to_string_lossy());
+    Ok(())
+}
+
 fn show_background_colors(config: &config::Config) {
     println!(
         "delta \
@@ -94,14 +158,148 @@ fn show_background_colors(config: &config::Config) {
     )
 }
 
+fn show_config(config: &config::Config) {
+    let paint_style = |style: &Style| style.ansi_term_style.paint(style.to_string());
+    let paint_decoration_style = |style: &Style| {
+        style
+            .decoration_ansi_term_style()
+            .unwrap_or_default()
+            .paint(style.decoration_style.to_string())
+    };
+    println!(
+        "    features                                   = {features}
+    theme                                      = {theme}
+    light-mode                                 = {light_mode}
+    true-color                                 = {true_color}
+    color-depth                                = {color_depth}
+    monochrome                                 = {monochrome}
+    width                                      = {width}
+    background-color-extends-to-terminal-width = {background_color_extends_to_terminal_width}
+    paging                                     = {paging}
+    output-format                              = {output_format}
+    json-syntax-scopes                         = {json_syntax_scopes}
+    tabs                                       = {tab_width}
+    map-syntax                                 = {map_syntax}
+    max-line-distance                          = {max_line_distance}
+    max-line-distance-for-naively-paired-lines = {max_line_distance_for_naively_paired_lines}
+    keep-plus-minus-markers                    = {keep_plus_minus_markers}
+    minus-line-marker                          = {minus_line_marker:?}
+    plus-line-marker                           = {plus_line_marker:?}
+    side-by-side                               = {side_by_side}
+    color-moved                                = {color_moved}
+    full-file-highlighting                     = {full_file_highlighting}
+    max-buffered-lines                         = {max_buffered_lines}",
+        features = config.features.join(" "),
+        theme = config.theme_name,
+        light_mode = config.is_light_mode,
+        true_color = config.true_color,
+        color_depth = match config.color_depth {
+            ColorDepth::Ansi16 => "16",
+            ColorDepth::Ansi256 => "256",
+            ColorDepth::TrueColor => "24bit",
+        },
+        monochrome = config.monochrome,
+        width = match config.decorations_width {
+            Width::Fixed(width) => width.to_string(),
+            Width::Variable => "variable".to_string(),
+        },
+        background_color_extends_to_terminal_width =
+            config.background_color_extends_to_terminal_width,
+        paging = match config.paging_mode {
+            PagingMode::Always => "always",
+            PagingMode::Never => "never",
+            PagingMode::QuitIfOneScreen => "auto",
+        },
+        output_format = format!("{:?}", config.output_format).to_lowercase(),
+        json_syntax_scopes = config.json_syntax_scopes,
+        tab_width = config.tab_width,
+        map_syntax = config.syntax_mapping.to_strings().join(" "),
+        max_line_distance = config.max_line_distance,
+        max_line_distance_for_naively_paired_lines =
+            config.max_line_distance_for_naively_paired_lines,
+        keep_plus_minus_markers = config.keep_plus_minus_markers,
+        minus_line_marker = config.minus_line_marker,
+        plus_line_marker = config.plus_line_marker,
+        side_by_side = config.side_by_side,
+        color_moved = config.color_moved,
+        full_file_highlighting = config.full_file_highlighting,
+        max_buffered_lines = config.max_buffered_lines,
+    );
+    println!(
+        "    minus-style                                = {minus_style}
+    minus-non-emph-style                       = {minus_non_emph_style}
+    minus-emph-style                           = {minus_emph_style}
+    zero-style                                 = {zero_style}
+    plus-style                                 = {plus_style}
+    plus-non-emph-style                        = {plus_non_emph_style}
+    plus-emph-style                            = {plus_emph_style}
+    whitespace-error-style                     = {whitespace_error_style}
+    moved-from-style                           = {moved_from_style}
+    moved-to-style                             = {moved_to_style}
+    commit-style                               = {commit_style}
+    commit-decoration-style                    = {commit_decoration_style}
+    file-style                                 = {file_style}
+    file-decoration-style                      = {file_decoration_style}
+    hunk-header-style                          = {hunk_header_style}
+    hunk-header-decoration-style               = {hunk_header_decoration_style}",
+        minus_style = paint_style(&config.minus_style),
+        minus_non_emph_style = paint_style(&config.minus_non_emph_style),
+        minus_emph_style = paint_style(&config.minus_emph_style),
+        zero_style = paint_style(&config.zero_style),
+        plus_style = paint_style(&config.plus_style),
+        plus_non_emph_style = paint_style(&config.plus_non_emph_style),
+        plus_emph_style = paint_style(&config.plus_emph_style),
+        whitespace_error_style = paint_style(&config.whitespace_error_style),
+        moved_from_style = paint_style(&config.moved_from_style),
+        moved_to_style = paint_style(&config.moved_to_style),
+        commit_style = paint_style(&config.commit_style),
+        commit_decoration_style = paint_decoration_style(&config.commit_style),
+        file_style = paint_style(&config.file_style),
+        file_decoration_style = paint_decoration_style(&config.file_style),
+        hunk_header_style = paint_style(&config.hunk_header_style),
+        hunk_header_decoration_style = paint_decoration_style(&config.hunk_header_style),
+    );
+    println!(
+        "    line-numbers                               = {line_numbers}
+    line-numbers-minus-style                   = {line_numbers_minus_style}
+    line-numbers-zero-style                    = {line_numbers_zero_style}
+    line-numbers-plus-style                    = {line_numbers_plus_style}
+    line-numbers-left-style                    = {line_numbers_left_style}
+    line-numbers-right-style                   = {line_numbers_right_style}
+    line-numbers-left-format                   = {line_numbers_left_format:?}
+    line-numbers-right-format                  = {line_numbers_right_format:?}
+    hyperlinks                                 = {hyperlinks}
+    hyperlinks-file-link-format                = {hyperlinks_file_link_format:?}
+    hyperlinks-commit-link-format              = {hyperlinks_commit_link_format:?}",
+        line_numbers = config.line_numbers,
+        line_numbers_minus_style = paint_style(&config.line_numbers_minus_style),
+        line_numbers_zero_style = paint_style(&config.line_numbers_zero_style),
+        line_numbers_plus_style = paint_style(&config.line_numbers_plus_style),
+        line_numbers_left_style = paint_style(&config.line_numbers_left_style),
+        line_numbers_right_style = paint_style(&config.line_numbers_right_style),
+        line_numbers_left_format = config.line_numbers_left_format,
+        line_numbers_right_format = config.line_numbers_right_format,
+        hyperlinks = config.hyperlinks,
+        hyperlinks_file_link_format = config.hyperlinks_file_link_format,
+        hyperlinks_commit_link_format = config
+            .hyperlinks_commit_link_format
+            .as_deref()
+            .unwrap_or(""),
+    );
+}
+
 fn get_painted_rgb_string(color: Color) -> String {
-    color.paint(format!("{:?}", color)).to_string()
+    let color_string = match color {
+        Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Fixed(n) => n.to_string(),
+        color => format!("{:?}", color).to_lowercase(),
+    };
+    color.paint(color_string).to_string()
 }
 
-fn list_themes() -> std::io::Result<()> {
+fn list_themes(opt: &cli::Opt) -> std::io::Result<()> {
     use bytelines::ByteLines;
     use std::io::BufReader;
-    let opt = cli::Opt::from_args();
     let input = if !atty::is(atty::Stream::Stdin) {
         let mut buf = Vec::new();
         io::stdin().lock().read_to_end(&mut buf)?;
@@ -132,7 +330,8 @@ index f38589a..0f1bb83 100644
     let assets = HighlightingAssets::new();
 
     for (theme, _) in assets.theme_set.themes.iter() {
-        if opt.light && !theme::is_light_theme(theme) || opt.dark && theme::is_light_theme(theme) {
+        let is_light_theme = theme::is_light_theme(theme, &assets.theme_set);
+        if opt.light && !is_light_theme || opt.dark && is_light_theme {
             continue;
         }
 
@@ -168,13 +367,13 @@ pub fn list_theme_names() -> std::io::Result<()> {
 
     writeln!(stdout, "Light themes:")?;
     for (theme, _) in themes.iter() {
-        if theme::is_light_theme(theme) {
+        if theme::is_light_theme(theme, &assets.theme_set) {
             writeln!(stdout, "    {}", theme)?;
         }
     }
     writeln!(stdout, "Dark themes:")?;
     for (theme, _) in themes.iter() {
-        if !theme::is_light_theme(theme) {
+        if !theme::is_light_theme(theme, &assets.theme_set) {
             writeln!(stdout, "    {}", theme)?;
         }
     }
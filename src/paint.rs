@@ -1,103 +1,434 @@
+use std::cmp::{max, min};
+use std::fs;
 use std::io::Write;
 
-use ansi_term;
+use git_delta::parse::{self, State};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::Style as SyntectStyle;
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::parsing::SyntaxReference;
 
+use crate::color_moved;
 use crate::config;
-use crate::delta::State;
 use crate::edits;
+use crate::git;
+use crate::line_numbers;
 use crate::paint::superimpose_style_sections::superimpose_style_sections;
+use crate::render;
+use crate::side_by_side;
 use crate::style::Style;
+use crate::word_diff;
 
 pub const ANSI_CSI_ERASE_IN_LINE: &str = "\x1b[K";
 pub const ANSI_SGR_RESET: &str = "\x1b[0m";
 
+/// The style sections of the minus and plus lines, and the alignment of homologous lines.
+type DiffStyleSections<'a> = (
+    Vec<Vec<(Style, &'a str)>>,
+    Vec<Vec<(Style, &'a str)>>,
+    Vec<(Option<usize>, Option<usize>)>,
+);
+
 pub struct Painter<'a> {
     pub minus_lines: Vec<String>,
     pub plus_lines: Vec<String>,
+    pub minus_line_prefixes: Vec<String>,
+    pub plus_line_prefixes: Vec<String>,
+    pub plus_line_whitespace_errors: Vec<Vec<(usize, usize)>>,
     pub writer: &'a mut dyn Write,
-    pub syntax: &'a SyntaxReference,
-    pub highlighter: HighlightLines<'a>,
+    pub minus_syntax: &'a SyntaxReference,
+    pub plus_syntax: &'a SyntaxReference,
+    pub minus_highlighter: HighlightLines<'a>,
+    pub plus_highlighter: HighlightLines<'a>,
+    /// Whether the file paths did not determine the minus and plus syntaxes, so that they are to
+    /// be detected from the content.
+    is_minus_syntax_undetermined: bool,
+    is_plus_syntax_undetermined: bool,
+    /// The lines of the minus and plus versions of the current file, as read under
+    /// --full-file-highlighting in order to prime the highlighters at the start of each hunk.
+    minus_file_lines: Option<Vec<String>>,
+    plus_file_lines: Option<Vec<String>>,
+    /// The number of lines of the minus and plus versions of the current file that the
+    /// highlighters have been brought past, under --full-file-highlighting.
+    minus_file_line_count: usize,
+    plus_file_line_count: usize,
     pub config: &'a config::Config<'a>,
     pub output_buffer: String,
+    pub line_numbers_data: Option<line_numbers::LineNumbersData<'a>>,
+    pub moved_lines: Option<color_moved::MovedLines>,
+    pub word_diff_data: Option<word_diff::WordDiffData>,
 }
 
 impl<'a> Painter<'a> {
     pub fn new(writer: &'a mut dyn Write, config: &'a config::Config) -> Self {
-        let default_syntax = Self::get_syntax(&config.syntax_set, None);
+        let default_syntax = Self::get_syntax(config, None);
         // TODO: Avoid doing this.
-        let dummy_highlighter = HighlightLines::new(default_syntax, &config.dummy_theme);
+        let dummy_highlighter = || HighlightLines::new(default_syntax, &config.dummy_theme);
+        let line_numbers_data = if config.line_numbers {
+            Some(line_numbers::LineNumbersData::from_format_strings(
+                &config.line_numbers_left_format,
+                &config.line_numbers_right_format,
+            ))
+        } else {
+            None
+        };
         Self {
             minus_lines: Vec::new(),
             plus_lines: Vec::new(),
+            minus_line_prefixes: Vec::new(),
+            plus_line_prefixes: Vec::new(),
+            plus_line_whitespace_errors: Vec::new(),
             output_buffer: String::new(),
-            syntax: default_syntax,
-            highlighter: dummy_highlighter,
+            minus_syntax: default_syntax,
+            plus_syntax: default_syntax,
+            minus_highlighter: dummy_highlighter(),
+            plus_highlighter: dummy_highlighter(),
+            is_minus_syntax_undetermined: false,
+            is_plus_syntax_undetermined: false,
+            minus_file_lines: None,
+            plus_file_lines: None,
+            minus_file_line_count: 0,
+            plus_file_line_count: 0,
             writer,
             config,
+            line_numbers_data,
+            moved_lines: None,
+            word_diff_data: None,
+        }
+    }
+
+    /// Set the syntaxes of the minus and plus versions of a file from their paths, falling back
+    /// to plain text. A side whose path is /dev/null (i.e. the file is added or deleted) takes
+    /// the syntax of the other side. Return whether either syntax was not determined by the
+    /// paths.
+    pub fn set_syntaxes(&mut self, minus_file: &str, plus_file: &str) -> bool {
+        let (minus_file, plus_file) = match (minus_file, plus_file) {
+            ("/dev/null", plus_file) => (plus_file, plus_file),
+            (minus_file, "/dev/null") => (minus_file, minus_file),
+            paths => paths,
+        };
+        let minus_syntax = Painter::find_syntax(self.config, Some(minus_file));
+        let plus_syntax = Painter::find_syntax(self.config, Some(plus_file));
+        self.minus_syntax = minus_syntax.unwrap_or_else(|| Painter::get_syntax(self.config, None));
+        self.plus_syntax = plus_syntax.unwrap_or_else(|| Painter::get_syntax(self.config, None));
+        self.is_minus_syntax_undetermined = minus_syntax.is_none();
+        self.is_plus_syntax_undetermined = plus_syntax.is_none();
+        self.is_minus_syntax_undetermined || self.is_plus_syntax_undetermined
+    }
+
+    /// Set the syntaxes that the file paths did not determine from the first line of the
+    /// content: a shebang, an Emacs or Vim modeline, or something like `<?xml`. If that does not
+    /// determine them either, the syntaxes are left unchanged.
+    pub fn set_syntax_from_first_line(&mut self, line: &str) {
+        if !self.is_minus_syntax_undetermined && !self.is_plus_syntax_undetermined {
+            return;
+        }
+        let syntax_set = &self.config.syntax_set;
+        let syntax = syntax_set.find_syntax_by_first_line(line).or_else(|| {
+            parse::get_syntax_name_from_modeline(line)
+                .and_then(|name| syntax_set.find_syntax_by_token(name))
+        });
+        if let Some(syntax) = syntax {
+            if self.is_minus_syntax_undetermined {
+                self.minus_syntax = syntax;
+            }
+            if self.is_plus_syntax_undetermined {
+                self.plus_syntax = syntax;
+            }
+            self.is_minus_syntax_undetermined = false;
+            self.is_plus_syntax_undetermined = false;
+            self.set_highlighters();
+        }
+    }
+
+    /// Read the contents of the minus and plus versions of the current file, for
+    /// --full-file-highlighting. If the blob hashes from the diff's index line are given, a
+    /// version is read from the git object database, except that the plus version of a diff
+    /// against the working tree is read from the working tree, since git does not have it.
+    /// Otherwise, as for `diff -u`, both versions are read from the given paths.
+    pub fn set_file_contents(
+        &mut self,
+        minus_file: &str,
+        plus_file: &str,
+        blob_hashes: Option<(&str, &str)>,
+    ) {
+        let read_file = |path: &str| {
+            let path = match (&self.config.git_root, blob_hashes) {
+                (Some(git_root), Some(_)) => git_root.join(path),
+                _ => path.into(),
+            };
+            fs::read_to_string(path).ok()
+        };
+        let minus_contents = match (minus_file, blob_hashes) {
+            ("/dev/null", _) => None,
+            (_, Some((minus_hash, _))) => git::get_blob_contents(minus_hash),
+            (minus_file, None) => read_file(minus_file),
+        };
+        let plus_contents = match (plus_file, blob_hashes) {
+            ("/dev/null", _) => None,
+            (plus_file, Some((minus_hash, plus_hash))) => {
+                git::get_blob_contents(plus_hash).or_else(|| {
+                    // `git diff` compares the index with the working tree.
+                    match git::get_index_blob_hash(minus_file) {
+                        Some(index_hash) if index_hash.starts_with(minus_hash) => {
+                            read_file(plus_file)
+                        }
+                        _ => None,
+                    }
+                })
+            }
+            (plus_file, None) => read_file(plus_file),
+        };
+        let get_lines = |contents: Option<String>| {
+            contents.map(|contents| {
+                contents
+                    .lines()
+                    .map(|line| format!(" {}\n", line))
+                    .collect::<Vec<String>>()
+            })
+        };
+        self.minus_file_lines = get_lines(minus_contents);
+        self.plus_file_lines = get_lines(plus_contents);
+        let first_line = self
+            .plus_file_lines
+            .as_ref()
+            .or(self.minus_file_lines.as_ref())
+            .and_then(|lines| lines.first())
+            .map(|line| line[1..].to_string());
+        if let Some(first_line) = first_line {
+            self.set_syntax_from_first_line(&first_line);
         }
+        self.minus_file_line_count = 0;
+        self.plus_file_line_count = 0;
+        self.set_highlighters();
     }
 
-    pub fn set_syntax(&mut self, extension: Option<&str>) {
-        self.syntax = Painter::get_syntax(&self.config.syntax_set, extension);
+    pub fn clear_file_contents(&mut self) {
+        self.minus_file_lines = None;
+        self.plus_file_lines = None;
     }
 
-    fn get_syntax(syntax_set: &'a SyntaxSet, extension: Option<&str>) -> &'a SyntaxReference {
-        syntax_set
-            .find_syntax_by_extension(extension.unwrap_or("txt"))
-            .unwrap_or_else(|| Painter::get_syntax(syntax_set, Some("txt")))
+    /// Prepare the highlighters for a hunk, given the (start, count) line number ranges from its
+    /// header. If the contents of the file are known, bring them to the parse state at the start
+    /// of the hunk by highlighting the lines of the file between the end of the previous hunk and
+    /// the start of this one. A hunk that starts inside e.g. a block comment or multi-line string
+    /// is then highlighted correctly. Otherwise, reset them.
+    pub fn prime_highlighters(&mut self, line_numbers: &[(usize, usize)]) {
+        if self.minus_file_lines.is_none() && self.plus_file_lines.is_none() {
+            self.set_highlighters();
+            return;
+        }
+        let (minus_range, plus_range) = match (line_numbers.first(), line_numbers.last()) {
+            (Some(minus_range), Some(plus_range)) => (*minus_range, *plus_range),
+            _ => return,
+        };
+        let config = self.config;
+        if Self::should_compute_syntax_highlighting(&State::HunkMinus, config) {
+            if let Some(lines) = &self.minus_file_lines {
+                Self::advance_highlighter(
+                    &mut self.minus_highlighter,
+                    lines,
+                    self.minus_file_line_count,
+                    minus_range,
+                    config,
+                );
+            }
+        }
+        if Self::should_compute_syntax_highlighting(&State::HunkPlus, config)
+            || Self::should_compute_syntax_highlighting(&State::HunkZero, config)
+        {
+            if let Some(lines) = &self.plus_file_lines {
+                Self::advance_highlighter(
+                    &mut self.plus_highlighter,
+                    lines,
+                    self.plus_file_line_count,
+                    plus_range,
+                    config,
+                );
+            }
+        }
+        // Painting the lines of the hunk brings the highlighters to its end.
+        self.minus_file_line_count = get_hunk_end(minus_range);
+        self.plus_file_line_count = get_hunk_end(plus_range);
     }
 
-    pub fn set_highlighter(&mut self) {
+    /// Highlight the lines of the file from `line_count` up to the start of the hunk.
+    fn advance_highlighter(
+        highlighter: &mut HighlightLines,
+        file_lines: &[String],
+        line_count: usize,
+        hunk_range: (usize, usize),
+        config: &config::Config,
+    ) {
+        let (start, count) = hunk_range;
+        let hunk_start = get_hunk_end((start, count)) - count;
+        for line in file_lines.iter().take(hunk_start).skip(line_count) {
+            highlighter.highlight(line, &config.syntax_set);
+        }
+    }
+
+    /// Return a highlighter for text that is highlighted out of the context of the file, such as
+    /// the code fragment of a hunk header.
+    pub fn get_out_of_context_highlighter(&self) -> HighlightLines<'a> {
+        let theme = self
+            .config
+            .theme
+            .as_ref()
+            .unwrap_or(&self.config.dummy_theme);
+        HighlightLines::new(self.plus_syntax, theme)
+    }
+
+    /// Return the syntax for a file: that given by the --map-syntax rules if one of them matches
+    /// the file path, else that for the file extension, else plain text.
+    pub fn get_syntax(config: &'a config::Config, file_path: Option<&str>) -> &'a SyntaxReference {
+        Self::find_syntax(config, file_path).unwrap_or_else(|| {
+            config
+                .syntax_set
+                .find_syntax_by_extension("txt")
+                .unwrap_or_else(|| config.syntax_set.find_syntax_plain_text())
+        })
+    }
+
+    fn find_syntax(
+        config: &'a config::Config,
+        file_path: Option<&str>,
+    ) -> Option<&'a SyntaxReference> {
+        let file_path = file_path?;
+        config
+            .syntax_mapping
+            .get_syntax(file_path, &config.syntax_set)
+            .or_else(|| {
+                parse::get_file_extension_from_file_meta_line_file_path(file_path)
+                    .and_then(|extension| config.syntax_set.find_syntax_by_extension(extension))
+            })
+    }
+
+    pub fn set_highlighters(&mut self) {
         if let Some(ref theme) = self.config.theme {
-            self.highlighter = HighlightLines::new(self.syntax, &theme)
+            self.minus_highlighter = HighlightLines::new(self.minus_syntax, theme);
+            self.plus_highlighter = HighlightLines::new(self.plus_syntax, theme);
         };
     }
 
+    /// Zero lines are painted using the plus highlighter. Pass them also through the minus
+    /// highlighter, so that its parse state follows the minus version of the file.
+    pub fn update_minus_highlighter_with_zero_lines(&mut self, lines: &[String]) {
+        if Self::should_compute_syntax_highlighting(&State::HunkMinus, self.config) {
+            for line in lines {
+                self.minus_highlighter
+                    .highlight(line, &self.config.syntax_set);
+            }
+        }
+    }
+
     pub fn paint_buffered_lines(&mut self) {
         let minus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
             &self.minus_lines,
             &State::HunkMinus,
-            &mut self.highlighter,
+            &mut self.minus_highlighter,
             self.config,
         );
         let plus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
             &self.plus_lines,
             &State::HunkPlus,
-            &mut self.highlighter,
+            &mut self.plus_highlighter,
             self.config,
         );
-        let (minus_line_diff_style_sections, plus_line_diff_style_sections) =
-            Self::get_diff_style_sections(&self.minus_lines, &self.plus_lines, self.config);
+        let (mut minus_line_diff_style_sections, mut plus_line_diff_style_sections, line_alignment) =
+            match &self.word_diff_data {
+                Some(word_diff_data) => (
+                    word_diff::get_diff_style_sections_for_lines(
+                        &self.minus_lines,
+                        &word_diff_data.minus_line_emph_ranges,
+                        self.config.minus_style,
+                        self.config.minus_emph_style,
+                        self.config.minus_non_emph_style,
+                    ),
+                    word_diff::get_diff_style_sections_for_lines(
+                        &self.plus_lines,
+                        &word_diff_data.plus_line_emph_ranges,
+                        self.config.plus_style,
+                        self.config.plus_emph_style,
+                        self.config.plus_non_emph_style,
+                    ),
+                    word_diff_data.line_alignment.clone(),
+                ),
+                None => {
+                    Self::get_diff_style_sections(&self.minus_lines, &self.plus_lines, self.config)
+                }
+            };
+        if let Some(moved_lines) = &self.moved_lines {
+            Self::set_moved_styles(
+                &mut minus_line_diff_style_sections,
+                &self.minus_lines,
+                &moved_lines.get_moved_from_lines(&self.minus_lines),
+                self.config.moved_from_style,
+            );
+            Self::set_moved_styles(
+                &mut plus_line_diff_style_sections,
+                &self.plus_lines,
+                &moved_lines.get_moved_to_lines(&self.plus_lines),
+                self.config.moved_to_style,
+            );
+        }
+        if !self.config.whitespace_error_style.is_omitted {
+            Self::set_whitespace_error_styles(
+                &mut plus_line_diff_style_sections,
+                &self.plus_line_whitespace_errors,
+                self.config.whitespace_error_style,
+            );
+        }
         // TODO: lines and style sections contain identical line text
-        if !self.minus_lines.is_empty() {
-            Painter::paint_lines(
+        if self.config.side_by_side {
+            side_by_side::paint_minus_and_plus_lines_side_by_side(
                 minus_line_syntax_style_sections,
                 minus_line_diff_style_sections,
-                &mut self.output_buffer,
-                self.config,
-                self.config.minus_line_marker,
-                self.config.minus_style,
-                self.config.minus_non_emph_style,
-                None,
-            );
-        }
-        if !self.plus_lines.is_empty() {
-            Painter::paint_lines(
                 plus_line_syntax_style_sections,
                 plus_line_diff_style_sections,
+                &self.minus_line_prefixes,
+                &self.plus_line_prefixes,
+                line_alignment,
                 &mut self.output_buffer,
                 self.config,
-                self.config.plus_line_marker,
-                self.config.plus_style,
-                self.config.plus_non_emph_style,
-                None,
+                &mut self.line_numbers_data,
             );
+        } else {
+            if !self.minus_lines.is_empty() {
+                Painter::paint_lines(
+                    minus_line_syntax_style_sections,
+                    minus_line_diff_style_sections,
+                    &State::HunkMinus,
+                    &mut self.output_buffer,
+                    self.config,
+                    &mut self.line_numbers_data,
+                    &self.minus_line_prefixes,
+                    self.config.minus_style,
+                    self.config.minus_non_emph_style,
+                    None,
+                );
+            }
+            if !self.plus_lines.is_empty() {
+                Painter::paint_lines(
+                    plus_line_syntax_style_sections,
+                    plus_line_diff_style_sections,
+                    &State::HunkPlus,
+                    &mut self.output_buffer,
+                    self.config,
+                    &mut self.line_numbers_data,
+                    &self.plus_line_prefixes,
+                    self.config.plus_style,
+                    self.config.plus_non_emph_style,
+                    None,
+                );
+            }
         }
         self.minus_lines.clear();
         self.plus_lines.clear();
+        self.minus_line_prefixes.clear();
+        self.plus_line_prefixes.clear();
+        self.plus_line_whitespace_errors.clear();
+        if let Some(word_diff_data) = &mut self.word_diff_data {
+            *word_diff_data = word_diff::WordDiffData::default();
+        }
     }
 
     /// Superimpose background styles and foreground syntax
@@ -105,76 +436,109 @@ impl<'a> Painter<'a> {
     pub fn paint_lines(
         syntax_style_sections: Vec<Vec<(SyntectStyle, &str)>>,
         diff_style_sections: Vec<Vec<(Style, &str)>>,
+        state: &State,
         output_buffer: &mut String,
         config: &config::Config,
-        prefix: &str,
-        style: Style,          // style for right fill if line contains no emph sections
+        line_numbers_data: &mut Option<line_numbers::LineNumbersData>,
+        prefixes: &[String], // the prefix to display in place of each line's -/+/space prefix
+        style: Style,        // style for right fill if line contains no emph sections
         non_emph_style: Style, // style for right fill if line contains emph sections
         background_color_extends_to_terminal_width: Option<bool>,
     ) {
-        // There's some unfortunate hackery going on here for two reasons:
-        //
-        // 1. The prefix needs to be injected into the output stream. We paint
-        //    this with whatever style the line starts with.
-        //
-        // 2. We must ensure that we fill rightwards with the appropriate
-        //    non-emph background color. In that case we don't use the last
-        //    style of the line, because this might be emph.
-        for (syntax_sections, diff_sections) in
-            syntax_style_sections.iter().zip(diff_style_sections.iter())
+        for ((syntax_sections, diff_sections), prefix) in syntax_style_sections
+            .iter()
+            .zip(diff_style_sections.iter())
+            .zip(prefixes.iter())
         {
-            let non_emph_style = if style_sections_contain_more_than_one_style(diff_sections) {
-                non_emph_style // line contains an emph section
-            } else {
-                style
-            };
-            let mut ansi_strings = Vec::new();
-            let mut handled_prefix = false;
-            for (section_style, mut text) in superimpose_style_sections(
+            let (style_sections, right_fill_style) = Self::get_line_style_sections(
                 syntax_sections,
                 diff_sections,
-                config.true_color,
-                config.null_syntect_style,
-            ) {
-                if !handled_prefix {
-                    if prefix != "" {
-                        ansi_strings.push(section_style.ansi_term_style.paint(prefix));
-                        if text.len() > 0 {
-                            text.remove(0);
-                        }
-                    }
-                    handled_prefix = true;
-                }
-                ansi_strings.push(section_style.ansi_term_style.paint(text));
+                config,
+                prefix,
+                style,
+                non_emph_style,
+            );
+            let mut ansi_strings = Vec::new();
+            if let Some(line_numbers_data) = line_numbers_data {
+                ansi_strings.extend(line_numbers::format_and_paint_line_numbers(
+                    line_numbers_data,
+                    state,
+                    None,
+                    config,
+                ))
             }
+            ansi_strings.extend(
+                style_sections.iter().map(|(section_style, text)| {
+                    section_style.ansi_term_style.paint(text.as_str())
+                }),
+            );
             // Set style for the right-fill.
-            let mut have_background_for_right_fill = false;
-            if non_emph_style.ansi_term_style.background.is_some() {
-                ansi_strings.push(non_emph_style.ansi_term_style.paint(""));
-                have_background_for_right_fill = true;
+            let right_fill_background = right_fill_style.ansi_term_style.background;
+            if right_fill_background.is_some() {
+                ansi_strings.push(right_fill_style.ansi_term_style.paint(""));
             }
-            let line = &mut ansi_term::ANSIStrings(&ansi_strings).to_string();
             let background_color_extends_to_terminal_width =
                 match background_color_extends_to_terminal_width {
                     Some(boolean) => boolean,
                     None => config.background_color_extends_to_terminal_width,
                 };
-            if background_color_extends_to_terminal_width && have_background_for_right_fill {
-                // HACK: How to properly incorporate the ANSI_CSI_ERASE_IN_LINE into ansi_strings?
-                if line
-                    .to_lowercase()
-                    .ends_with(&ANSI_SGR_RESET.to_lowercase())
-                {
-                    line.truncate(line.len() - ANSI_SGR_RESET.len());
+            output_buffer.push_str(&render::get_renderer(config).paint_line(
+                &ansi_strings,
+                if background_color_extends_to_terminal_width {
+                    right_fill_background
+                } else {
+                    None
+                },
+            ));
+        }
+    }
+
+    /// Superimpose background styles and foreground syntax highlighting styles for a single line.
+    /// Return the resulting style sections, with the prefix injected, together with the style that
+    /// should be used to fill rightwards beyond the end of the line.
+    pub fn get_line_style_sections(
+        syntax_sections: &[(SyntectStyle, &str)],
+        diff_sections: &[(Style, &str)],
+        config: &config::Config,
+        prefix: &str,
+        style: Style,          // style for right fill if line contains no emph sections
+        non_emph_style: Style, // style for right fill if line contains emph sections
+    ) -> (Vec<(Style, String)>, Style) {
+        // There's some unfortunate hackery going on here for two reasons:
+        //
+        // 1. The prefix needs to be injected into the output stream. We paint
+        //    this with whatever style the line starts with.
+        //
+        // 2. We must ensure that we fill rightwards with the appropriate
+        //    non-emph background color. In that case we don't use the last
+        //    style of the line, because this might be emph.
+        let right_fill_style = if style_sections_contain_more_than_one_style(diff_sections) {
+            non_emph_style // line contains an emph section
+        } else if config.color_moved && Self::is_moved_line(diff_sections, config) {
+            diff_sections[0].0
+        } else {
+            style
+        };
+        let mut style_sections = Vec::new();
+        let mut handled_prefix = false;
+        for (section_style, mut text) in superimpose_style_sections(
+            syntax_sections,
+            diff_sections,
+            config.true_color,
+            config.null_syntect_style,
+        ) {
+            if !handled_prefix {
+                if !prefix.is_empty() {
+                    style_sections.push((section_style, prefix.to_string()));
+                    if !text.is_empty() {
+                        text.remove(0);
+                    }
                 }
-                output_buffer.push_str(&line);
-                output_buffer.push_str(ANSI_CSI_ERASE_IN_LINE);
-                output_buffer.push_str(ANSI_SGR_RESET);
-            } else {
-                output_buffer.push_str(&line);
+                handled_prefix = true;
             }
-            output_buffer.push_str("\n");
+            style_sections.push((section_style, text));
         }
+        (style_sections, right_fill_style)
     }
 
     /// Write output buffer to output stream, and clear the buffer.
@@ -224,12 +588,13 @@ impl<'a> Painter<'a> {
         line_sections
     }
 
-    /// Set background styles to represent diff for minus and plus lines in buffer.
+    /// Set background styles to represent diff for minus and plus lines in buffer. Also return
+    /// the alignment of homologous minus and plus lines.
     fn get_diff_style_sections<'b>(
         minus_lines: &'b Vec<String>,
         plus_lines: &'b Vec<String>,
         config: &config::Config,
-    ) -> (Vec<Vec<(Style, &'b str)>>, Vec<Vec<(Style, &'b str)>>) {
+    ) -> DiffStyleSections<'b> {
         let mut diff_sections = edits::infer_edits(
             minus_lines,
             plus_lines,
@@ -249,6 +614,75 @@ impl<'a> Painter<'a> {
         diff_sections
     }
 
+    /// Paint moved lines entirely in the moved style, replacing any inferred edit operations.
+    fn set_moved_styles<'b>(
+        style_sections: &mut Vec<Vec<(Style, &'b str)>>,
+        lines: &'b [String],
+        moved: &[bool],
+        moved_style: Style,
+    ) {
+        for ((line_sections, line), is_moved) in style_sections
+            .iter_mut()
+            .zip(lines.iter())
+            .zip(moved.iter())
+        {
+            if *is_moved {
+                *line_sections = vec![(moved_style, line.as_str())];
+            }
+        }
+    }
+
+    /// Split style sections so that the byte ranges of whitespace errors in each line are painted
+    /// with the whitespace error style.
+    fn set_whitespace_error_styles(
+        style_sections: &mut Vec<Vec<(Style, &str)>>,
+        whitespace_errors: &[Vec<(usize, usize)>],
+        whitespace_error_style: Style,
+    ) {
+        for (line_sections, ranges) in style_sections.iter_mut().zip(whitespace_errors.iter()) {
+            if ranges.is_empty() {
+                continue;
+            }
+            let mut new_line_sections = Vec::new();
+            let mut section_start = 0;
+            for (style, text) in line_sections.iter() {
+                let section_end = section_start + text.len();
+                let mut offset = section_start;
+                for (range_start, range_end) in ranges {
+                    let start = max(*range_start, offset);
+                    let end = min(*range_end, section_end);
+                    if start < end {
+                        if offset < start {
+                            new_line_sections.push((
+                                *style,
+                                &text[offset - section_start..start - section_start],
+                            ));
+                        }
+                        new_line_sections.push((
+                            whitespace_error_style,
+                            &text[start - section_start..end - section_start],
+                        ));
+                        offset = end;
+                    }
+                }
+                if offset < section_end {
+                    new_line_sections.push((*style, &text[offset - section_start..]));
+                }
+                section_start = section_end;
+            }
+            *line_sections = new_line_sections;
+        }
+    }
+
+    fn is_moved_line(diff_sections: &[(Style, &str)], config: &config::Config) -> bool {
+        match diff_sections.first() {
+            Some((style, _)) => {
+                *style == config.moved_from_style || *style == config.moved_to_style
+            }
+            None => false,
+        }
+    }
+
     fn set_non_emph_styles(style_sections: &mut Vec<Vec<(Style, &str)>>, non_emph_style: Style) {
         for line_sections in style_sections {
             // If there multiple diff styles in the line, then the line must have some inferred
@@ -267,7 +701,7 @@ impl<'a> Painter<'a> {
 
 // edits::annotate doesn't return "coalesced" annotations (see comment there), so we can't assume
 // that `sections.len() > 1 <=> (multiple styles)`.
-fn style_sections_contain_more_than_one_style(sections: &Vec<(Style, &str)>) -> bool {
+fn style_sections_contain_more_than_one_style(sections: &[(Style, &str)]) -> bool {
     if sections.len() > 1 {
         let (first_style, _) = sections[0];
         sections
@@ -280,6 +714,17 @@ fn style_sections_contain_more_than_one_style(sections: &Vec<(Style, &str)>) ->
     }
 }
 
+/// Return the number of lines of the file up to the end of a hunk, given its (start, count) line
+/// number range. A hunk with no lines of the file (e.g. a range of lines added at the start of a
+/// file, "-0,0") gives as its start the line that it follows.
+fn get_hunk_end((start, count): (usize, usize)) -> usize {
+    if count == 0 {
+        start
+    } else {
+        start.saturating_sub(1) + count
+    }
+}
+
 mod superimpose_style_sections {
     use syntect::highlighting::Style as SyntectStyle;
 
@@ -1,18 +1,40 @@
 use std::path::Path;
 
-// https://git-scm.com/docs/git-config#Documentation/git-config.txt-diffmnemonicPrefix
-const DIFF_PREFIXES: [&str; 6] = ["a", "b", "c", "i", "o", "w"];
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
 
-/// Given input like
-/// "--- one.rs	2019-11-20 06:16:08.000000000 +0100"
-/// Return "rs"
-pub fn get_file_extension_from_marker_line(line: &str) -> Option<&str> {
-    line.split('\t')
-        .next()
-        .and_then(|column| column.split(' ').nth(1))
-        .and_then(|file| file.split('.').last())
+#[derive(Clone, Debug, PartialEq)]
+pub enum State {
+    CommitMeta, // In commit metadata section
+    DiffStat,   // In diffstat section, e.g. as output by `git show --stat`
+    FileMeta,   // In diff metadata section, between (possible) commit metadata and first hunk
+    HunkHeader, // In hunk metadata line
+    HunkZero,   // In hunk; unchanged line
+    HunkMinus,  // In hunk; removed line
+    HunkPlus,   // In hunk; added line
+    Unknown,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    GitDiff,     // Coming from a `git diff` command
+    DiffUnified, // Coming from a `diff -u` command
+    Unknown,
+}
+
+impl State {
+    pub fn is_in_hunk(&self) -> bool {
+        match *self {
+            State::HunkHeader | State::HunkZero | State::HunkMinus | State::HunkPlus => true,
+            _ => false,
+        }
+    }
+}
+
+// https://git-scm.com/docs/git-config#Documentation/git-config.txt-diffmnemonicPrefix
+const DIFF_PREFIXES: [&str; 6] = ["a", "b", "c", "i", "o", "w"];
+
 pub fn get_file_path_from_file_meta_line(line: &str, git_diff_name: bool) -> String {
     match line {
         line if line.starts_with("rename from ") => {
@@ -39,6 +61,19 @@ pub fn get_file_path_from_file_meta_line(line: &str, git_diff_name: bool) -> Str
     .to_string()
 }
 
+/// Given "diff --git a/src/main.rs b/src/main.rs", return Some("src/main.rs").
+pub fn get_old_path_from_diff_line(line: &str) -> Option<String> {
+    let paths = line.strip_prefix("diff --git a/")?;
+    let end = paths.find(" b/")?;
+    Some(paths[..end].to_string())
+}
+
+pub fn get_new_path_from_diff_line(line: &str) -> Option<String> {
+    let paths = line.strip_prefix("diff --git a/")?;
+    let start = paths.find(" b/")?;
+    Some(paths[start + " b/".len()..].to_string())
+}
+
 pub fn get_file_extension_from_file_meta_line_file_path(path: &str) -> Option<&str> {
     if path.is_empty() || path == "/dev/null" {
         None
@@ -66,15 +101,129 @@ pub fn get_file_change_description_from_file_paths(
 
 /// Given input like
 /// "@@ -74,15 +74,14 @@ pub fn delta("
-/// Return " pub fn delta("
-pub fn parse_hunk_metadata(line: &str) -> (&str, &str) {
-    let mut iter = line.split("@@").skip(1);
-    let line_number = iter
+/// Return " pub fn delta(" together with the line number ranges [(74, 15), (74, 14)]. Each range is a
+/// (start, count) pair; the last range is that of the plus file. The hunk header of a combined diff
+/// ("@@@ -1,5 -1,4 +1,6 @@@") has one minus range per parent, preceding the plus range.
+pub fn parse_hunk_metadata(line: &str) -> (&str, Vec<(usize, usize)>) {
+    let marker = "@".repeat(get_number_of_parents_from_hunk_header_line(line) + 1);
+    let mut iter = line.splitn(3, marker.as_str()).skip(1);
+    let line_number_ranges = iter
         .next()
-        .and_then(|s| s.split('+').nth(1).and_then(|s| s.split(',').next()))
-        .unwrap_or("");
+        .map(|s| {
+            s.split_whitespace()
+                .filter_map(parse_line_number_range)
+                .collect()
+        })
+        .unwrap_or_default();
     let code_fragment = iter.next().unwrap_or("");
-    (code_fragment, line_number)
+    (code_fragment, line_number_ranges)
+}
+
+/// Return the number of parents of the diff to which a hunk header belongs. An ordinary diff has a
+/// single parent, and its hunk headers start with "@@". A combined diff, as output by git for a
+/// merge commit with N parents, has hunk headers starting with N + 1 '@' characters.
+pub fn get_number_of_parents_from_hunk_header_line(line: &str) -> usize {
+    let n_markers = line.chars().take_while(|c| *c == '@').count();
+    if n_markers > 2 {
+        n_markers - 1
+    } else {
+        1
+    }
+}
+
+/// Classify a hunk line according to its prefix, which has one column per parent. In a combined
+/// diff, a line with a '-' in some column is present in that parent but has been removed from the
+/// merge result, and a line with a '+' in some column is absent from that parent but present in
+/// the merge result. Return None if the line does not have a valid prefix.
+pub fn get_hunk_line_state(line: &str, n_parents: usize) -> Option<State> {
+    let prefix = line.get(..n_parents)?;
+    if prefix.is_empty() || !prefix.chars().all(|c| c == '-' || c == '+' || c == ' ') {
+        None
+    } else if prefix.contains('-') {
+        Some(State::HunkMinus)
+    } else if prefix.contains('+') {
+        Some(State::HunkPlus)
+    } else {
+        Some(State::HunkZero)
+    }
+}
+
+/// Given input like "-74,15" or "+74", return the (start, count) pair. The count defaults to 1
+/// when it is omitted.
+fn parse_line_number_range(s: &str) -> Option<(usize, usize)> {
+    let mut iter = s.trim_start_matches(&['-', '+'][..]).split(',');
+    let start = iter.next()?.parse().ok()?;
+    let count = match iter.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+    Some((start, count))
+}
+
+/// A line of a diffstat, as output by `git diff --stat`.
+#[derive(Debug, PartialEq)]
+pub struct DiffStatLine<'a> {
+    pub path: &'a str,
+    pub path_column_width: usize, // display width of the path, including git's alignment padding
+    pub count: &'a str, // the number of changed lines, or a description of a binary change
+    pub bar: &'a str,   // the bar of '+' and '-' characters
+}
+
+lazy_static! {
+    static ref DIFF_STAT_LINE_REGEX: Regex = Regex::new(
+        r"^ (?P<path>\S(?:.*\S)?)(?P<padding> *) \| (?P<count> *\d+|Bin \d+ -> \d+ bytes) ?(?P<bar>[+-]*)$"
+    )
+    .unwrap();
+    static ref EMACS_MODELINE_REGEX: Regex =
+        Regex::new(r"-\*-(?:.*;)?\s*(?:mode:\s*)?([\w+#-]+)\s*(?:;.*)?-\*-").unwrap();
+    static ref VIM_MODELINE_REGEX: Regex =
+        Regex::new(r"\b(?:vi|vim|ex):.*\b(?:ft|filetype|syntax)=([\w+#-]+)").unwrap();
+    static ref INDEX_LINE_REGEX: Regex =
+        Regex::new(r"^index ([0-9a-f]+)\.\.([0-9a-f]+)(?: \d+)?$").unwrap();
+    static ref DIFF_STAT_SUMMARY_LINE_REGEX: Regex = Regex::new(
+        r"^ \d+ files? changed(?:, \d+ insertions?\(\+\))?(?:, \d+ deletions?\(-\))?$"
+    )
+    .unwrap();
+}
+
+/// Return the name of the language given by an Emacs modeline, such as
+/// "# -*- mode: python; coding: utf-8 -*-", or a Vim modeline, such as "# vim: set ft=python:".
+pub fn get_syntax_name_from_modeline(line: &str) -> Option<&str> {
+    EMACS_MODELINE_REGEX
+        .captures(line)
+        .or_else(|| VIM_MODELINE_REGEX.captures(line))
+        .and_then(|captures| captures.get(1))
+        .map(|name| name.as_str())
+}
+
+/// Given input like
+/// "index 1f3f7b1..4c4e7c2 100644"
+/// Return the (abbreviated) hashes of the minus and plus blobs, "1f3f7b1" and "4c4e7c2". The index
+/// line of a combined diff, which has a hash for each parent, is not handled.
+pub fn get_blob_hashes_from_index_line(line: &str) -> Option<(&str, &str)> {
+    let captures = INDEX_LINE_REGEX.captures(line)?;
+    Some((captures.get(1)?.as_str(), captures.get(2)?.as_str()))
+}
+
+/// Given input like
+/// " src/delta.rs   | 25 +++++++++++++++-------"
+/// Return the path "src/delta.rs", together with the width of the path column (15), the count
+/// "25", and the bar "+++++++++++++++-------".
+pub fn parse_diff_stat_line(line: &str) -> Option<DiffStatLine<'_>> {
+    let captures = DIFF_STAT_LINE_REGEX.captures(line)?;
+    let path = captures.name("path")?.as_str();
+    Some(DiffStatLine {
+        path,
+        path_column_width: UnicodeWidthStr::width(path) + captures.name("padding")?.as_str().len(),
+        count: captures.name("count")?.as_str(),
+        bar: captures.name("bar")?.as_str(),
+    })
+}
+
+/// Is the line the summary line of a diffstat, e.g.
+/// " 2 files changed, 6 insertions(+), 5 deletions(-)"?
+pub fn is_diff_stat_summary_line(line: &str) -> bool {
+    DIFF_STAT_SUMMARY_LINE_REGEX.is_match(line)
 }
 
 /// Attempt to parse input as a file path and return extension as a &str.
@@ -86,18 +235,52 @@ fn get_extension(s: &str) -> Option<&str> {
         .or_else(|| path.file_name().and_then(|s| s.to_str()))
 }
 
+/// Try to detect what is producing the input for delta.
+///
+/// Currently can detect:
+/// * git diff
+/// * diff -u
+pub fn detect_source(line: &str) -> Source {
+    if line.starts_with("commit ")
+        || line.starts_with("diff --git ")
+        || line.starts_with("diff --cc ")
+        || line.starts_with("diff --combined ")
+    {
+        Source::GitDiff
+    } else if line.starts_with("diff -u ")
+        || line.starts_with("diff -U")
+        || line.starts_with("--- ")
+    {
+        Source::DiffUnified
+    } else {
+        Source::Unknown
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_get_file_extension_from_marker_line() {
+    fn test_get_syntax_name_from_modeline() {
         assert_eq!(
-            get_file_extension_from_marker_line(
-                "--- src/one.rs	2019-11-20 06:47:56.000000000 +0100"
-            ),
-            Some("rs")
+            get_syntax_name_from_modeline("# -*- mode: python; coding: utf-8 -*-"),
+            Some("python")
+        );
+        assert_eq!(
+            get_syntax_name_from_modeline(";; -*- lisp -*-"),
+            Some("lisp")
         );
+        assert_eq!(
+            get_syntax_name_from_modeline("# vim: set ft=sh ts=4:"),
+            Some("sh")
+        );
+        assert_eq!(
+            get_syntax_name_from_modeline("/* vim: filetype=c */"),
+            Some("c")
+        );
+        assert_eq!(get_syntax_name_from_modeline("#!/bin/sh"), None);
+        assert_eq!(get_syntax_name_from_modeline("x = a - b * c - d"), None);
     }
 
     #[test]
@@ -228,7 +411,122 @@ mod tests {
     fn test_parse_hunk_metadata() {
         assert_eq!(
             parse_hunk_metadata("@@ -74,15 +75,14 @@ pub fn delta(\n"),
-            (" pub fn delta(\n", "75")
+            (" pub fn delta(\n", vec![(74, 15), (75, 14)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_metadata_with_omitted_line_counts() {
+        assert_eq!(
+            parse_hunk_metadata("@@ -1 +1,2 @@\n"),
+            ("\n", vec![(1, 1), (1, 2)])
+        );
+        assert_eq!(
+            parse_hunk_metadata("@@ -0,0 +1 @@"),
+            ("", vec![(0, 0), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_metadata_of_combined_diff() {
+        assert_eq!(
+            parse_hunk_metadata("@@@ -1,5 -1,4 +1,6 @@@ fn main() {"),
+            (" fn main() {", vec![(1, 5), (1, 4), (1, 6)])
         );
     }
+
+    #[test]
+    fn test_get_number_of_parents_from_hunk_header_line() {
+        assert_eq!(
+            get_number_of_parents_from_hunk_header_line("@@ -1,2 +1,2 @@"),
+            1
+        );
+        assert_eq!(
+            get_number_of_parents_from_hunk_header_line("@@@ -1,2 -1,2 +1,2 @@@"),
+            2
+        );
+        assert_eq!(
+            get_number_of_parents_from_hunk_header_line("@@@@ -1 -1 -1 +1 @@@@"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_get_blob_hashes_from_index_line() {
+        assert_eq!(
+            get_blob_hashes_from_index_line("index 1f3f7b1..4c4e7c2 100644"),
+            Some(("1f3f7b1", "4c4e7c2"))
+        );
+        assert_eq!(
+            get_blob_hashes_from_index_line("index 0000000..8c55b7d"),
+            Some(("0000000", "8c55b7d"))
+        );
+        assert_eq!(
+            get_blob_hashes_from_index_line("index 1f3f7b1,4c4e7c2..8c55b7d"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_hunk_line_state() {
+        assert_eq!(get_hunk_line_state("-a", 1), Some(State::HunkMinus));
+        assert_eq!(get_hunk_line_state("+a", 1), Some(State::HunkPlus));
+        assert_eq!(get_hunk_line_state(" a", 1), Some(State::HunkZero));
+        assert_eq!(get_hunk_line_state("\\ No newline", 1), None);
+        assert_eq!(get_hunk_line_state("", 1), None);
+
+        assert_eq!(get_hunk_line_state("--a", 2), Some(State::HunkMinus));
+        assert_eq!(get_hunk_line_state(" -a", 2), Some(State::HunkMinus));
+        assert_eq!(get_hunk_line_state("++a", 2), Some(State::HunkPlus));
+        assert_eq!(get_hunk_line_state("+ a", 2), Some(State::HunkPlus));
+        assert_eq!(get_hunk_line_state(" +a", 2), Some(State::HunkPlus));
+        assert_eq!(get_hunk_line_state("  a", 2), Some(State::HunkZero));
+        assert_eq!(get_hunk_line_state("-a", 2), None);
+    }
+
+    #[test]
+    fn test_parse_diff_stat_line() {
+        assert_eq!(
+            parse_diff_stat_line(" src/delta.rs   | 25 +++++++++++++++-------"),
+            Some(DiffStatLine {
+                path: "src/delta.rs",
+                path_column_width: 14,
+                count: "25",
+                bar: "+++++++++++++++-------",
+            })
+        );
+        assert_eq!(
+            parse_diff_stat_line(" a b | c.txt |  0"),
+            Some(DiffStatLine {
+                path: "a b | c.txt",
+                path_column_width: 11,
+                count: " 0",
+                bar: "",
+            })
+        );
+        assert_eq!(
+            parse_diff_stat_line(" logo.png | Bin 0 -> 1024 bytes"),
+            Some(DiffStatLine {
+                path: "logo.png",
+                path_column_width: 8,
+                count: "Bin 0 -> 1024 bytes",
+                bar: "",
+            })
+        );
+        assert_eq!(parse_diff_stat_line("    Commit message | 1 +"), None);
+        assert_eq!(
+            parse_diff_stat_line(" 1 file changed, 1 insertion(+)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_diff_stat_summary_line() {
+        assert!(is_diff_stat_summary_line(
+            " 2 files changed, 6 insertions(+), 5 deletions(-)"
+        ));
+        assert!(is_diff_stat_summary_line(" 1 file changed, 1 insertion(+)"));
+        assert!(is_diff_stat_summary_line(" 1 file changed, 3 deletions(-)"));
+        assert!(!is_diff_stat_summary_line("    2 files changed"));
+    }
 }
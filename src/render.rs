@@ -0,0 +1,390 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use ansi_term::{self, ANSIString, ANSIStrings, Color};
+use console::strip_ansi_codes;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::color;
+use crate::config::{ColorDepth, Config, OutputFormat, Width};
+use crate::draw;
+use crate::html::MarkupRenderer;
+use crate::hyperlinks;
+use crate::paint::{ANSI_CSI_ERASE_IN_LINE, ANSI_SGR_RESET};
+use crate::style::DecorationStyle;
+
+/// A renderer converts computed style sections, and the decorations of commit, file, and hunk
+/// headers, into the text that is written to the output stream. The style sections are passed as
+/// ANSIStrings, i.e. (style, text) pairs, as they are computed by `Painter`.
+pub trait Renderer {
+    /// Return text painted in a style.
+    fn paint(&self, style: ansi_term::Style, text: &str) -> String;
+
+    /// Return text that is already painted with ANSI escape sequences, e.g. a raw line of input.
+    fn paint_ansi_text(&self, text: &str) -> String;
+
+    /// Return a sequence of style sections.
+    fn paint_sections(&self, sections: &[ANSIString]) -> String;
+
+    /// Return a line, terminated by a newline. If `right_fill_background` is not None, the line's
+    /// background color is extended rightwards to the width of the terminal.
+    fn paint_line(&self, sections: &[ANSIString], right_fill_background: Option<Color>) -> String;
+
+    /// Write text (already painted by this renderer) decorated with a box, or with lines above or
+    /// below it.
+    fn write_decorated(
+        &self,
+        writer: &mut dyn Write,
+        text: &str,
+        line_width: &Width,
+        decoration_style: DecorationStyle,
+    ) -> std::io::Result<()>;
+}
+
+/// Terminal output, using ANSI escape sequences with 24-bit, 256, or 16 colors.
+pub struct AnsiRenderer {
+    pub color_depth: ColorDepth,
+}
+
+/// Terminal output, using ANSI escape sequences for attributes such as bold, underline, and
+/// reverse, but no colors.
+pub struct MonochromeRenderer;
+
+/// Text without any styles. Decorations are drawn with box-drawing characters.
+pub struct PlainRenderer;
+
+static ANSI_24_BIT_RENDERER: AnsiRenderer = AnsiRenderer {
+    color_depth: ColorDepth::TrueColor,
+};
+static ANSI_256_RENDERER: AnsiRenderer = AnsiRenderer {
+    color_depth: ColorDepth::Ansi256,
+};
+static ANSI_16_RENDERER: AnsiRenderer = AnsiRenderer {
+    color_depth: ColorDepth::Ansi16,
+};
+static MONOCHROME_RENDERER: MonochromeRenderer = MonochromeRenderer;
+static PLAIN_RENDERER: PlainRenderer = PlainRenderer;
+static MARKUP_RENDERER: MarkupRenderer = MarkupRenderer;
+
+/// Return the renderer for the output format and colors of the configuration.
+pub fn get_renderer(config: &Config) -> &'static dyn Renderer {
+    match config.output_format {
+        OutputFormat::Html => &MARKUP_RENDERER,
+        OutputFormat::Plain => &PLAIN_RENDERER,
+        _ if config.monochrome => &MONOCHROME_RENDERER,
+        _ => match config.color_depth {
+            ColorDepth::TrueColor => &ANSI_24_BIT_RENDERER,
+            ColorDepth::Ansi256 => &ANSI_256_RENDERER,
+            ColorDepth::Ansi16 => &ANSI_16_RENDERER,
+        },
+    }
+}
+
+impl AnsiRenderer {
+    /// Return the style with 24-bit colors replaced by the closest of the 256 colors, unless the
+    /// terminal supports 24-bit color.
+    fn get_style(&self, style: ansi_term::Style) -> ansi_term::Style {
+        if self.color_depth == ColorDepth::TrueColor {
+            return style;
+        }
+        let to_256_color = |color: Color| match color {
+            Color::RGB(r, g, b) => Color::Fixed(ansi_colours::ansi256_from_rgb((r, g, b))),
+            color => color,
+        };
+        ansi_term::Style {
+            foreground: style.foreground.map(to_256_color),
+            background: style.background.map(to_256_color),
+            ..style
+        }
+    }
+
+    fn get_ansi_strings<'a>(&self, sections: &'a [ANSIString<'a>]) -> Vec<ANSIString<'a>> {
+        sections
+            .iter()
+            .map(|section| self.get_style(*section.style_ref()).paint(&**section))
+            .collect()
+    }
+}
+
+/// Return text painted in a style, with each color replaced by the nearest of the 16 basic colors.
+/// ansi_term writes colors 0-15 as 256-color codes, which 16-color terminals do not understand,
+/// so the SGR codes are written here: 30-37 and 90-97 for foregrounds, and 40-47 and 100-107 for
+/// backgrounds.
+fn paint_16_color(style: ansi_term::Style, text: &str) -> String {
+    let mut codes = Vec::new();
+    for (is_set, code) in &[
+        (style.is_bold, 1),
+        (style.is_dimmed, 2),
+        (style.is_italic, 3),
+        (style.is_underline, 4),
+        (style.is_blink, 5),
+        (style.is_reverse, 7),
+        (style.is_hidden, 8),
+        (style.is_strikethrough, 9),
+    ] {
+        if *is_set {
+            codes.push(*code);
+        }
+    }
+    let get_code = |color: Color, base: u8| match color::get_ansi_16_color_number(color) {
+        n if n < 8 => base + n,
+        n => base + 60 + n - 8,
+    };
+    if let Some(color) = style.foreground {
+        codes.push(get_code(color, 30));
+    }
+    if let Some(color) = style.background {
+        codes.push(get_code(color, 40));
+    }
+    if codes.is_empty() {
+        return text.to_string();
+    }
+    let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+    format!("\x1b[{}m{}{}", codes.join(";"), text, ANSI_SGR_RESET)
+}
+
+impl Renderer for AnsiRenderer {
+    fn paint(&self, style: ansi_term::Style, text: &str) -> String {
+        if self.color_depth == ColorDepth::Ansi16 {
+            return paint_16_color(style, text);
+        }
+        self.get_style(style).paint(text).to_string()
+    }
+
+    fn paint_ansi_text(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn paint_sections(&self, sections: &[ANSIString]) -> String {
+        if self.color_depth == ColorDepth::Ansi16 {
+            return sections
+                .iter()
+                .map(|section| paint_16_color(*section.style_ref(), section))
+                .collect();
+        }
+        ANSIStrings(&self.get_ansi_strings(sections)).to_string()
+    }
+
+    fn paint_line(&self, sections: &[ANSIString], right_fill_background: Option<Color>) -> String {
+        let mut line = self.paint_sections(sections);
+        if right_fill_background.is_some() {
+            // HACK: How to properly incorporate the ANSI_CSI_ERASE_IN_LINE into ansi_strings?
+            if line
+                .to_lowercase()
+                .ends_with(&ANSI_SGR_RESET.to_lowercase())
+            {
+                line.truncate(line.len() - ANSI_SGR_RESET.len());
+            }
+            line.push_str(ANSI_CSI_ERASE_IN_LINE);
+            line.push_str(ANSI_SGR_RESET);
+        }
+        line.push('\n');
+        line
+    }
+
+    fn write_decorated(
+        &self,
+        writer: &mut dyn Write,
+        text: &str,
+        line_width: &Width,
+        decoration_style: DecorationStyle,
+    ) -> std::io::Result<()> {
+        draw::write_decorated(writer, self, text, line_width, decoration_style)
+    }
+}
+
+lazy_static! {
+    static ref SGR_SEQUENCE_REGEX: Regex = Regex::new(r"\x1b\[([0-9;]*)m").unwrap();
+}
+
+/// Return text with the colors removed from its SGR escape sequences, keeping the attributes.
+fn remove_colors(text: &str) -> Cow<'_, str> {
+    SGR_SEQUENCE_REGEX.replace_all(text, |captures: &Captures| {
+        let mut parameters = captures[1].split(';');
+        let mut attributes = Vec::new();
+        while let Some(parameter) = parameters.next() {
+            match parameter.parse::<u8>() {
+                // An extended color: 5;n (256 colors) or 2;r;g;b (24-bit).
+                Ok(38) | Ok(48) | Ok(58) => match parameters.next() {
+                    Some("5") => {
+                        parameters.next();
+                    }
+                    Some("2") => {
+                        parameters.nth(2);
+                    }
+                    _ => {}
+                },
+                Ok(30..=37) | Ok(39) | Ok(40..=47) | Ok(49) | Ok(90..=97) | Ok(100..=107) => {}
+                _ => attributes.push(parameter),
+            }
+        }
+        if attributes.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", attributes.join(";"))
+        }
+    })
+}
+
+impl MonochromeRenderer {
+    fn get_style(&self, style: ansi_term::Style) -> ansi_term::Style {
+        ansi_term::Style {
+            foreground: None,
+            background: None,
+            ..style
+        }
+    }
+}
+
+impl Renderer for MonochromeRenderer {
+    fn paint(&self, style: ansi_term::Style, text: &str) -> String {
+        self.get_style(style).paint(remove_colors(text)).to_string()
+    }
+
+    fn paint_ansi_text(&self, text: &str) -> String {
+        remove_colors(text).to_string()
+    }
+
+    fn paint_sections(&self, sections: &[ANSIString]) -> String {
+        let sections: Vec<ANSIString> = sections
+            .iter()
+            .map(|section| {
+                self.get_style(*section.style_ref())
+                    .paint(remove_colors(section))
+            })
+            .collect();
+        ANSIStrings(&sections).to_string()
+    }
+
+    /// There is no background color to extend rightwards.
+    fn paint_line(&self, sections: &[ANSIString], _right_fill_background: Option<Color>) -> String {
+        format!("{}\n", self.paint_sections(sections))
+    }
+
+    fn write_decorated(
+        &self,
+        writer: &mut dyn Write,
+        text: &str,
+        line_width: &Width,
+        decoration_style: DecorationStyle,
+    ) -> std::io::Result<()> {
+        draw::write_decorated(writer, self, text, line_width, decoration_style)
+    }
+}
+
+impl Renderer for PlainRenderer {
+    fn paint(&self, _style: ansi_term::Style, text: &str) -> String {
+        hyperlinks::strip_osc8_hyperlinks(text).to_string()
+    }
+
+    fn paint_ansi_text(&self, text: &str) -> String {
+        strip_ansi_codes(&hyperlinks::strip_osc8_hyperlinks(text)).to_string()
+    }
+
+    fn paint_sections(&self, sections: &[ANSIString]) -> String {
+        sections
+            .iter()
+            .map(|section| self.paint_ansi_text(section))
+            .collect()
+    }
+
+    fn paint_line(&self, sections: &[ANSIString], _right_fill_background: Option<Color>) -> String {
+        format!("{}\n", self.paint_sections(sections))
+    }
+
+    fn write_decorated(
+        &self,
+        writer: &mut dyn Write,
+        text: &str,
+        line_width: &Width,
+        decoration_style: DecorationStyle,
+    ) -> std::io::Result<()> {
+        draw::write_decorated(writer, self, text, line_width, decoration_style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ansi_term::Color;
+
+    use super::*;
+
+    #[test]
+    fn test_ansi_256_renderer_converts_24_bit_colors() {
+        let style = ansi_term::Style::new().on(Color::RGB(0x00, 0x5f, 0x00));
+        assert_eq!(
+            ANSI_24_BIT_RENDERER.paint(style, "a"),
+            style.paint("a").to_string()
+        );
+        assert_eq!(
+            ANSI_256_RENDERER.paint(style, "a"),
+            ansi_term::Style::new()
+                .on(Color::Fixed(22))
+                .paint("a")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_ansi_16_renderer_uses_basic_color_codes() {
+        let style = Color::RGB(0xff, 0xff, 0xff).on(Color::Fixed(88)).bold();
+        assert_eq!(ANSI_16_RENDERER.paint(style, "a"), "\x1b[1;97;41ma\x1b[0m");
+        let sections = vec![
+            Color::Red.on(Color::Fixed(10)).paint("a"),
+            ansi_term::Style::new().paint("b"),
+        ];
+        assert_eq!(
+            ANSI_16_RENDERER.paint_line(&sections, None),
+            "\x1b[31;102ma\x1b[0mb\n"
+        );
+    }
+
+    #[test]
+    fn test_monochrome_renderer_keeps_attributes_only() {
+        let sections = vec![
+            Color::Red
+                .on(Color::RGB(0x3f, 0x00, 0x01))
+                .reverse()
+                .paint("a"),
+            Color::Fixed(10).paint("b"),
+        ];
+        assert_eq!(
+            MONOCHROME_RENDERER.paint_line(&sections, Some(Color::Red)),
+            "\x1b[7ma\x1b[0mb\n"
+        );
+        assert_eq!(
+            MONOCHROME_RENDERER
+                .paint_ansi_text("\x1b[1;38;5;208;48;2;1;2;3mbold\x1b[31mred\x1b[0m"),
+            "\x1b[1mboldred\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_plain_renderer() {
+        let style = Color::Red.bold();
+        let sections = vec![style.paint("a"), Color::Blue.paint("b")];
+        assert_eq!(
+            PLAIN_RENDERER.paint_line(&sections, Some(Color::Red)),
+            "ab\n"
+        );
+        assert_eq!(PLAIN_RENDERER.paint_ansi_text("\x1b[31mred\x1b[0m"), "red");
+    }
+
+    #[test]
+    fn test_plain_renderer_decorations() {
+        let mut writer: Vec<u8> = Vec::new();
+        PLAIN_RENDERER
+            .write_decorated(
+                &mut writer,
+                "text ",
+                &Width::Variable,
+                DecorationStyle::Box(Color::Blue.normal()),
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer).unwrap(),
+            "─────┐\ntext │\n─────┘\n"
+        );
+    }
+}
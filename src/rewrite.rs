@@ -1,7 +1,5 @@
-/// This module applies rewrite rules to the command line options, in order to
-/// 1. Express deprecated usages in the new non-deprecated form
-/// 2. Implement options such as --color-only which are defined to be equivalent to some set of
-///    other options.
+/// This module applies rewrite rules to the command line options, in order to express deprecated
+/// usages in the new non-deprecated form.
 use std::process;
 
 use crate::cli;
@@ -10,7 +8,6 @@ pub fn apply_rewrite_rules(opt: &mut cli::Opt) {
     _rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt);
     _rewrite_options_to_implement_deprecated_commit_and_file_style_box_option(opt);
     _rewrite_options_to_implement_deprecated_hunk_style_option(opt);
-    _rewrite_options_to_implement_color_only(opt);
 }
 
 #[cfg(test)]
@@ -57,20 +54,6 @@ mod tests {
     }
 }
 
-/// Implement --color-only
-fn _rewrite_options_to_implement_color_only(opt: &mut cli::Opt) {
-    if opt.color_only {
-        opt.keep_plus_minus_markers = true;
-        opt.tab_width = 0;
-        opt.commit_style = "raw".to_string();
-        opt.commit_decoration_style = "none".to_string();
-        opt.file_style = "raw".to_string();
-        opt.file_decoration_style = "none".to_string();
-        opt.hunk_header_style = "raw".to_string();
-        opt.hunk_header_decoration_style = "none".to_string();
-    }
-}
-
 /// Honor deprecated arguments by rewriting the canonical --*-style arguments if appropriate.
 // TODO: How to avoid repeating the default values for style options here and in
 // the structopt definition?
@@ -0,0 +1,357 @@
+use git_delta::parse::State;
+use syntect::highlighting::Style as SyntectStyle;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{Config, Width};
+use crate::line_numbers::{self, LineNumbersData};
+use crate::paint::Painter;
+use crate::render;
+use crate::style::Style;
+
+const TRUNCATION_MARKER: &str = "→";
+
+/// The syntax style sections, the diff style sections, and the display prefix of a single line.
+type LineSections<'a, 's> = (
+    &'a [(SyntectStyle, &'s str)],
+    &'a [(Style, &'s str)],
+    &'a str,
+);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanelSide {
+    Left,
+    Right,
+}
+
+/// Paint minus lines in the left panel and plus lines in the right panel. Homologous pairs of
+/// lines, as inferred by edits::infer_edits, are painted on the same row; a line without a
+/// homolog is painted opposite an empty panel.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_minus_and_plus_lines_side_by_side(
+    minus_syntax_style_sections: Vec<Vec<(SyntectStyle, &str)>>,
+    minus_diff_style_sections: Vec<Vec<(Style, &str)>>,
+    plus_syntax_style_sections: Vec<Vec<(SyntectStyle, &str)>>,
+    plus_diff_style_sections: Vec<Vec<(Style, &str)>>,
+    minus_line_prefixes: &[String],
+    plus_line_prefixes: &[String],
+    line_alignment: Vec<(Option<usize>, Option<usize>)>,
+    output_buffer: &mut String,
+    config: &Config,
+    line_numbers_data: &mut Option<LineNumbersData>,
+) {
+    for (minus_line_index, plus_line_index) in line_alignment {
+        // A panel without a line takes the state of the line opposite it, so that no line number
+        // is displayed in its gutter.
+        let (left_state, right_state) = match (minus_line_index, plus_line_index) {
+            (Some(_), Some(_)) => (State::HunkMinus, State::HunkPlus),
+            (Some(_), None) => (State::HunkMinus, State::HunkMinus),
+            _ => (State::HunkPlus, State::HunkPlus),
+        };
+        output_buffer.push_str(&paint_panel_line(
+            minus_line_index.map(|i| {
+                (
+                    &minus_syntax_style_sections[i][..],
+                    &minus_diff_style_sections[i][..],
+                    minus_line_prefixes[i].as_str(),
+                )
+            }),
+            PanelSide::Left,
+            &left_state,
+            config.minus_style,
+            config.minus_non_emph_style,
+            config,
+            line_numbers_data,
+        ));
+        output_buffer.push_str(&paint_panel_line(
+            plus_line_index.map(|i| {
+                (
+                    &plus_syntax_style_sections[i][..],
+                    &plus_diff_style_sections[i][..],
+                    plus_line_prefixes[i].as_str(),
+                )
+            }),
+            PanelSide::Right,
+            &right_state,
+            config.plus_style,
+            config.plus_non_emph_style,
+            config,
+            line_numbers_data,
+        ));
+        output_buffer.push('\n');
+    }
+}
+
+/// Paint unchanged lines, identically, in both panels.
+pub fn paint_zero_lines_side_by_side(
+    syntax_style_sections: Vec<Vec<(SyntectStyle, &str)>>,
+    diff_style_sections: Vec<Vec<(Style, &str)>>,
+    output_buffer: &mut String,
+    config: &Config,
+    line_numbers_data: &mut Option<LineNumbersData>,
+    prefix: &str,
+) {
+    for (syntax_sections, diff_sections) in
+        syntax_style_sections.iter().zip(diff_style_sections.iter())
+    {
+        for panel_side in &[PanelSide::Left, PanelSide::Right] {
+            output_buffer.push_str(&paint_panel_line(
+                Some((syntax_sections, diff_sections, prefix)),
+                *panel_side,
+                &State::HunkZero,
+                config.zero_style,
+                config.zero_style,
+                config,
+                line_numbers_data,
+            ));
+        }
+        output_buffer.push('\n');
+    }
+}
+
+/// Return the width of the panel on the given side. The two panels share the decorations width,
+/// or the terminal width if the decorations width is variable.
+pub fn get_panel_width(panel_side: PanelSide, config: &Config) -> usize {
+    let total_width = match config.decorations_width {
+        Width::Fixed(n) => n,
+        Width::Variable => config.available_terminal_width,
+    };
+    match panel_side {
+        PanelSide::Left => total_width / 2,
+        PanelSide::Right => total_width - total_width / 2,
+    }
+}
+
+/// Paint a single line (or an empty line if `sections` is None) into a panel, truncating it if it
+/// is wider than the panel. The left panel is always padded to its full width, so that the right
+/// panel starts in a fixed column; the padding carries the background color of the line only if
+/// background colors extend to the terminal width. If line numbers are displayed, the panel's
+/// gutter is included in its width.
+fn paint_panel_line(
+    sections: Option<LineSections>,
+    panel_side: PanelSide,
+    state: &State,
+    style: Style,
+    non_emph_style: Style,
+    config: &Config,
+    line_numbers_data: &mut Option<LineNumbersData>,
+) -> String {
+    let panel_width = get_panel_width(panel_side, config);
+    let mut ansi_strings = Vec::new();
+    if let Some(line_numbers_data) = line_numbers_data {
+        ansi_strings.extend(truncate_gutter(
+            line_numbers::format_and_paint_line_numbers(
+                line_numbers_data,
+                state,
+                Some(panel_side),
+                config,
+            ),
+            panel_width,
+        ));
+    }
+    let gutter_width = ansi_strings
+        .iter()
+        .map(|s| UnicodeWidthStr::width(&**s))
+        .sum::<usize>();
+    let panel_width = panel_width - gutter_width;
+    let (style_sections, right_fill_style) = match sections {
+        Some((syntax_sections, diff_sections, prefix)) => Painter::get_line_style_sections(
+            syntax_sections,
+            diff_sections,
+            config,
+            prefix,
+            style,
+            non_emph_style,
+        ),
+        None => (Vec::new(), config.null_style),
+    };
+    let (style_sections, text_width) = truncate_style_sections(style_sections, panel_width);
+
+    ansi_strings.extend(
+        style_sections
+            .iter()
+            .map(|(section_style, text)| section_style.ansi_term_style.paint(text.as_str())),
+    );
+    let fill = " ".repeat(panel_width.saturating_sub(text_width));
+    if config.background_color_extends_to_terminal_width
+        && right_fill_style.ansi_term_style.background.is_some()
+    {
+        ansi_strings.push(right_fill_style.ansi_term_style.paint(fill));
+    } else if panel_side == PanelSide::Left {
+        ansi_strings.push(config.null_style.ansi_term_style.paint(fill));
+    }
+    render::get_renderer(config).paint_sections(&ansi_strings)
+}
+
+/// Truncate the painted line number gutter so that its display width does not exceed the panel
+/// width.
+fn truncate_gutter(
+    ansi_strings: Vec<ansi_term::ANSIString<'static>>,
+    width: usize,
+) -> Vec<ansi_term::ANSIString<'static>> {
+    let mut truncated = Vec::new();
+    let mut truncated_width = 0;
+    for ansi_string in ansi_strings {
+        let text_width = UnicodeWidthStr::width(&*ansi_string);
+        if truncated_width + text_width <= width {
+            truncated_width += text_width;
+            truncated.push(ansi_string);
+            continue;
+        }
+        let mut truncated_text = String::new();
+        for grapheme in ansi_string.graphemes(true) {
+            truncated_width += UnicodeWidthStr::width(grapheme);
+            if truncated_width > width {
+                break;
+            }
+            truncated_text.push_str(grapheme);
+        }
+        truncated.push(ansi_string.style_ref().paint(truncated_text));
+        break;
+    }
+    truncated
+}
+
+/// Truncate style sections so that their total display width does not exceed `width`. If
+/// truncation is necessary, the final character displayed is a truncation marker, unless `width`
+/// is too small to hold the marker. Return the truncated sections together with their display
+/// width.
+fn truncate_style_sections(
+    style_sections: Vec<(Style, String)>,
+    width: usize,
+) -> (Vec<(Style, String)>, usize) {
+    let total_width = style_sections
+        .iter()
+        .map(|(_, text)| UnicodeWidthStr::width(text.as_str()))
+        .sum();
+    if total_width <= width {
+        return (style_sections, total_width);
+    }
+    let marker_width = UnicodeWidthStr::width(TRUNCATION_MARKER);
+    let (available_width, marker) = if width < marker_width {
+        (width, None)
+    } else {
+        (width - marker_width, Some(TRUNCATION_MARKER))
+    };
+    let mut truncated = Vec::new();
+    let mut truncated_width = 0;
+    'sections: for (style, text) in style_sections {
+        let mut truncated_text = String::new();
+        for grapheme in text.graphemes(true) {
+            let grapheme_width = UnicodeWidthStr::width(grapheme);
+            if truncated_width + grapheme_width > available_width {
+                truncated.push((style, truncated_text));
+                if let Some(marker) = marker {
+                    truncated.push((style, marker.to_string()));
+                    truncated_width += marker_width;
+                }
+                break 'sections;
+            }
+            truncated_text.push_str(grapheme);
+            truncated_width += grapheme_width;
+        }
+        truncated.push((style, truncated_text));
+    }
+    (truncated, truncated_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+    use unicode_width::UnicodeWidthStr;
+
+    use crate::style::Style;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::truncate_style_sections;
+
+    #[test]
+    fn test_side_by_side_homologous_lines_share_a_row() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.side_by_side = true;
+        options.width = Some("60".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, options);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+        assert!(lines.contains(
+            &format!(
+                "{:30}{}",
+                " fn print_square(num: f64) {", " fn print_cube(num: f64) {"
+            )
+            .as_str()
+        ));
+        assert!(lines.contains(&format!("{:30}{}", " }", " }").as_str()));
+    }
+
+    #[test]
+    fn test_side_by_side_unpaired_line_is_opposite_empty_panel() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.side_by_side = true;
+        options.width = Some("60".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, options);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+        assert!(lines.contains(&format!("{:30}{}", "", "     // Added comment.").as_str()));
+    }
+
+    #[test]
+    fn test_truncate_style_sections() {
+        let style = Style::new();
+        let sections = vec![(style, "abcd".to_string()), (style, "efgh".to_string())];
+        let (truncated, width) = truncate_style_sections(sections.clone(), 8);
+        assert_eq!(truncated, sections);
+        assert_eq!(width, 8);
+
+        let (truncated, width) = truncate_style_sections(sections, 6);
+        let text: String = truncated.iter().map(|(_, s)| s.as_str()).collect();
+        assert_eq!(text, "abcde→");
+        assert_eq!(width, 6);
+        assert_eq!(UnicodeWidthStr::width(text.as_str()), 6);
+    }
+
+    #[test]
+    fn test_side_by_side_gutter_wider_than_panel() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.side_by_side = true;
+        options.line_numbers = true;
+        options.width = Some("10".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, options);
+        let output = strip_ansi_codes(&output);
+        assert!(output
+            .lines()
+            .all(|line| UnicodeWidthStr::width(line.trim_end()) <= 10));
+    }
+
+    #[test]
+    fn test_truncate_style_sections_narrower_than_marker() {
+        let style = Style::new();
+        let sections = vec![(style, "abcd".to_string())];
+        let (truncated, width) = truncate_style_sections(sections, 0);
+        let text: String = truncated.iter().map(|(_, s)| s.as_str()).collect();
+        assert_eq!(text, "");
+        assert_eq!(width, 0);
+    }
+
+    #[test]
+    fn test_side_by_side_narrow_width_does_not_panic() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.side_by_side = true;
+        options.width = Some("1".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, options);
+        let output = strip_ansi_codes(&output);
+        assert!(output.lines().all(|line| !line.contains("print_cube")));
+    }
+
+    const GIT_DIFF_SINGLE_HUNK: &str = "\
+diff --git a/example.rs b/example.rs
+index f38589a..0f1bb83 100644
+--- a/example.rs
++++ b/example.rs
+@@ -1,4 +1,5 @@
+-fn print_square(num: f64) {
++fn print_cube(num: f64) {
++    // Added comment.
+     let result = f64::powf(num, 2.0);
+ }
+";
+}
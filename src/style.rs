@@ -1,3 +1,4 @@
+use std::fmt;
 use std::process;
 
 use ansi_term;
@@ -37,6 +38,12 @@ bitflags! {
     }
 }
 
+impl Default for Style {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Style {
     pub fn new() -> Self {
         Self {
@@ -181,6 +188,78 @@ impl Style {
     }
 }
 
+impl fmt::Display for Style {
+    /// Format the style as a style string, i.e. in the form accepted by the --*-style options.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_raw {
+            return write!(f, "raw");
+        }
+        if self.is_omitted {
+            return write!(f, "omit");
+        }
+        let mut words = Vec::<String>::new();
+        let ansi_term_style = self.ansi_term_style;
+        if self.is_syntax_highlighted {
+            words.push("syntax".to_string());
+        } else if let Some(color) = ansi_term_style.foreground {
+            words.push(color::color_to_string(color));
+        } else if ansi_term_style.background.is_some() {
+            words.push("normal".to_string());
+        }
+        if let Some(color) = ansi_term_style.background {
+            words.push(color::color_to_string(color));
+        }
+        words.extend(
+            get_attribute_names(ansi_term_style)
+                .iter()
+                .map(|s| s.to_string()),
+        );
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+impl fmt::Display for DecorationStyle {
+    /// Format the decoration style as a decoration style string, i.e. in the form accepted by the
+    /// --*-decoration-style options.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (style, special_attribute) = match self {
+            DecorationStyle::Box(style) => (style, "box"),
+            DecorationStyle::Underline(style) => (style, "ul"),
+            DecorationStyle::Overline(style) => (style, "ol"),
+            DecorationStyle::UnderOverline(style) => (style, "ul ol"),
+            DecorationStyle::BoxWithUnderline(style) => (style, "box ul"),
+            DecorationStyle::BoxWithOverline(style) => (style, "box ol"),
+            DecorationStyle::BoxWithUnderOverline(style) => (style, "box ul ol"),
+            DecorationStyle::NoDecoration => return write!(f, "none"),
+        };
+        let style = Style {
+            ansi_term_style: *style,
+            ..Style::new()
+        };
+        match style.to_string().as_ref() {
+            "" => write!(f, "{}", special_attribute),
+            style_string => write!(f, "{} {}", style_string, special_attribute),
+        }
+    }
+}
+
+fn get_attribute_names(style: ansi_term::Style) -> Vec<&'static str> {
+    [
+        (style.is_blink, "blink"),
+        (style.is_bold, "bold"),
+        (style.is_dimmed, "dim"),
+        (style.is_hidden, "hidden"),
+        (style.is_italic, "italic"),
+        (style.is_reverse, "reverse"),
+        (style.is_strikethrough, "strike"),
+        (style.is_underline, "ul"),
+    ]
+    .iter()
+    .filter(|(is_set, _)| *is_set)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
 impl DecorationStyle {
     pub fn from_str(style_string: &str, true_color: bool) -> Self {
         let (special_attributes, style_string) =
@@ -598,6 +677,31 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_style_to_string() {
+        for style_string in &[
+            "",
+            "raw",
+            "omit",
+            "syntax",
+            "red",
+            "normal #3f0001",
+            "syntax brightgreen bold ul",
+            "28 #aabbcc italic",
+        ] {
+            let style = Style::from_str(style_string, None, None, None, true, false);
+            assert_eq!(style.to_string(), *style_string);
+        }
+    }
+
+    #[test]
+    fn test_decoration_style_to_string() {
+        for decoration_style_string in &["none", "box", "blue ul", "red bold box ul ol"] {
+            let decoration_style = DecorationStyle::from_str(decoration_style_string, true);
+            assert_eq!(decoration_style.to_string(), *decoration_style_string);
+        }
+    }
+
     #[test]
     fn test_style_from_str_raw_with_box() {
         let actual_style = Style::from_str("raw", None, None, Some("box"), true, false);
@@ -0,0 +1,186 @@
+use std::path::Path;
+use std::process;
+
+use regex::Regex;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// Rules, given by --map-syntax, that choose the syntax used to highlight a file by matching a
+/// glob against its path. A later rule takes precedence over an earlier one.
+pub struct SyntaxMapping {
+    rules: Vec<SyntaxMappingRule>,
+}
+
+struct SyntaxMappingRule {
+    glob: String,
+    regex: Regex,
+    syntax_name: String,
+}
+
+impl SyntaxMapping {
+    /// Parse rules of the form '<glob>:<syntax name>', exiting with an error message if a rule is
+    /// malformed or names a syntax that is not in `syntax_set`.
+    pub fn from_strs(rules: &[String], syntax_set: &SyntaxSet) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let (glob, syntax_name) = match rule.rfind(':') {
+                    Some(i) if i > 0 && i < rule.len() - 1 => (&rule[..i], &rule[i + 1..]),
+                    _ => {
+                        eprintln!(
+                            "Invalid value for --map-syntax option: {} (expected '<glob>:<syntax name>', e.g. '*.conf:INI')",
+                            rule
+                        );
+                        process::exit(1);
+                    }
+                };
+                if find_syntax_by_name(syntax_set, syntax_name).is_none() {
+                    eprintln!(
+                        "Invalid syntax name in --map-syntax option: {} \
+                         (see delta --list-languages)",
+                        syntax_name
+                    );
+                    process::exit(1);
+                }
+                SyntaxMappingRule {
+                    glob: glob.to_string(),
+                    regex: glob_to_regex(glob),
+                    syntax_name: syntax_name.to_string(),
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Return the syntax given by the last rule whose glob matches either the whole path or the
+    /// file name.
+    pub fn get_syntax<'a>(
+        &self,
+        path: &str,
+        syntax_set: &'a SyntaxSet,
+    ) -> Option<&'a SyntaxReference> {
+        let file_name = Path::new(path).file_name().and_then(|s| s.to_str());
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                rule.regex.is_match(path)
+                    || file_name.is_some_and(|file_name| rule.regex.is_match(file_name))
+            })
+            .and_then(|rule| find_syntax_by_name(syntax_set, &rule.syntax_name))
+    }
+
+    /// Return the rules in the form in which they were given.
+    pub fn to_strings(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .map(|rule| format!("{}:{}", rule.glob, rule.syntax_name))
+            .collect()
+    }
+}
+
+fn find_syntax_by_name<'a>(syntax_set: &'a SyntaxSet, name: &str) -> Option<&'a SyntaxReference> {
+    syntax_set.find_syntax_by_name(name).or_else(|| {
+        syntax_set
+            .syntaxes()
+            .iter()
+            .find(|syntax| syntax.name.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Translate a glob to an anchored regex. '*' and '?' do not match '/', '**' matches anything,
+/// '[...]' is a character class (negated by a leading '!'), and '{a,b}' matches either
+/// alternative.
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    let mut in_alternatives = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in &mut chars {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            '{' => {
+                in_alternatives = true;
+                regex.push_str("(?:");
+            }
+            ',' if in_alternatives => regex.push('|'),
+            '}' if in_alternatives => {
+                in_alternatives = false;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).unwrap_or_else(|_| {
+        eprintln!("Invalid glob in --map-syntax option: {}", glob);
+        process::exit(1);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bat::assets::HighlightingAssets;
+
+    use super::{glob_to_regex, SyntaxMapping};
+
+    #[test]
+    fn test_glob_to_regex() {
+        let is_match = |glob, path| glob_to_regex(glob).is_match(path);
+        assert!(is_match("*.conf", "nginx.conf"));
+        assert!(!is_match("*.conf", "etc/nginx.conf"));
+        assert!(is_match("**/*.conf", "etc/nginx.conf"));
+        assert!(is_match("Jenkinsfile", "Jenkinsfile"));
+        assert!(!is_match("Jenkinsfile", "Jenkinsfile.bak"));
+        assert!(is_match("file.?", "file.c"));
+        assert!(is_match("*.[ch]", "main.h"));
+        assert!(!is_match("*.[!ch]", "main.h"));
+        assert!(is_match("*.{tpl,tmpl}", "page.tmpl"));
+        assert!(is_match("a+b.txt", "a+b.txt"));
+    }
+
+    #[test]
+    fn test_syntax_mapping() {
+        let syntax_set = HighlightingAssets::from_binary().syntax_set;
+        let syntax_mapping = SyntaxMapping::from_strs(
+            &[
+                "*.conf:INI".to_string(),
+                "Jenkinsfile:Groovy".to_string(),
+                "templates/*.tpl:HTML".to_string(),
+                "special.conf:YAML".to_string(),
+            ],
+            &syntax_set,
+        );
+        let get_syntax_name = |path| {
+            syntax_mapping
+                .get_syntax(path, &syntax_set)
+                .map(|syntax| syntax.name.as_str())
+        };
+        assert_eq!(get_syntax_name("etc/app.conf"), Some("INI"));
+        assert_eq!(get_syntax_name("etc/special.conf"), Some("YAML"));
+        assert_eq!(get_syntax_name("ci/Jenkinsfile"), Some("Groovy"));
+        assert_eq!(get_syntax_name("templates/page.tpl"), Some("HTML"));
+        assert_eq!(get_syntax_name("other/page.tpl"), None);
+        assert_eq!(get_syntax_name("src/main.rs"), None);
+    }
+}
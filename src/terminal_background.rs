@@ -0,0 +1,292 @@
+use std::io::{Read, Write};
+
+use crate::color;
+use crate::env;
+
+/// How long to wait for each byte of the terminal's replies, in tenths of a second. This allows
+/// for a terminal at the other end of a slow connection. A terminal that does not support the
+/// OSC 11 query does not cause a wait, since it answers the DA1 query that follows it.
+#[cfg(unix)]
+const QUERY_TIMEOUT_DECISECONDS: u8 = 10;
+
+/// A reply longer than this is not a reply to the query.
+const MAX_REPLY_LENGTH: usize = 64;
+
+/// Return whether the terminal background is light: as given by the COLORFGBG environment
+/// variable if it is set, else as reported by the terminal in reply to an OSC 11 query on the
+/// controlling tty. Return None if neither determines it.
+pub fn detect_is_light_background() -> Option<bool> {
+    match env::get_env_var("COLORFGBG") {
+        Some(colorfgbg) => is_light_background_from_colorfgbg(&colorfgbg),
+        None => query_controlling_terminal(),
+    }
+}
+
+/// COLORFGBG, as set by e.g. rxvt and Konsole, has the form "fg;bg" or "fg;default;bg", where fg
+/// and bg are ANSI color numbers. Backgrounds 0-6 and 8 are dark; the others are light.
+fn is_light_background_from_colorfgbg(colorfgbg: &str) -> Option<bool> {
+    let background: u8 = colorfgbg.rsplit(';').next()?.parse().ok()?;
+    Some(!matches!(background, 0..=6 | 8))
+}
+
+/// A reply from the terminal to a query.
+enum Reply {
+    /// An OSC sequence, such as the reply to the OSC 11 query, without its terminator.
+    OperatingSystemCommand(String),
+    /// A control sequence, such as the reply to the DA1 query.
+    ControlSequence,
+}
+
+/// Ask a terminal for its background color with an OSC 11 query, and return whether it is light.
+/// The terminal replies with e.g. "ESC ] 11 ; rgb:ffff/ffff/dddd", terminated by BEL or ST. The
+/// query is followed by a Primary Device Attributes (DA1) query, which every terminal answers: if
+/// that reply comes first, the terminal does not support the OSC 11 query. A read from `terminal`
+/// must return 0 bytes if no reply arrives in time; None is then returned.
+pub fn query_is_light_background<T: Read + Write>(terminal: &mut T) -> Option<bool> {
+    terminal.write_all(b"\x1b]11;?\x07\x1b[c").ok()?;
+    terminal.flush().ok()?;
+    let osc_11_reply = match read_reply(terminal)? {
+        Reply::OperatingSystemCommand(reply) => reply,
+        Reply::ControlSequence => return None,
+    };
+    // Consume the reply to the DA1 query, so that it is not left as input to the shell.
+    read_reply(terminal);
+    is_light_background_from_osc_11_reply(&osc_11_reply)
+}
+
+/// Read a reply from the terminal. Reading stops at the first byte that cannot be part of a
+/// reply, such as a key typed by the user before the query.
+fn read_reply<T: Read>(terminal: &mut T) -> Option<Reply> {
+    if read_byte(terminal)? != b'\x1b' {
+        return None;
+    }
+    match read_byte(terminal)? {
+        b']' => {
+            let mut reply = b"\x1b]".to_vec();
+            loop {
+                if reply.len() == MAX_REPLY_LENGTH {
+                    return None;
+                }
+                match read_byte(terminal)? {
+                    b'\x07' => break,
+                    b'\\' if reply.last() == Some(&b'\x1b') => {
+                        reply.pop();
+                        break;
+                    }
+                    byte => reply.push(byte),
+                }
+            }
+            Some(Reply::OperatingSystemCommand(
+                String::from_utf8_lossy(&reply).to_string(),
+            ))
+        }
+        b'[' => {
+            // A control sequence ends with a byte in the range @ to ~.
+            for _ in 0..MAX_REPLY_LENGTH {
+                if let 0x40..=0x7e = read_byte(terminal)? {
+                    return Some(Reply::ControlSequence);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn read_byte<T: Read>(terminal: &mut T) -> Option<u8> {
+    let mut byte = [0; 1];
+    match terminal.read(&mut byte).ok()? {
+        0 => None,
+        _ => Some(byte[0]),
+    }
+}
+
+fn is_light_background_from_osc_11_reply(reply: &str) -> Option<bool> {
+    let rgb = reply.strip_prefix("\x1b]11;rgb:")?;
+    // Each component has 1 to 4 hex digits, scaled to its maximum value.
+    let components = rgb
+        .split('/')
+        .map(|component| match component.len() {
+            1..=4 => u32::from_str_radix(component, 16)
+                .ok()
+                .map(|value| value as f64 / ((1 << (4 * component.len())) - 1) as f64),
+            _ => None,
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    match components[..] {
+        [r, g, b] => Some(color::is_light_rgb(r, g, b)),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn query_controlling_terminal() -> Option<bool> {
+    query_is_light_background(&mut tty::Tty::open()?)
+}
+
+#[cfg(not(unix))]
+fn query_controlling_terminal() -> Option<bool> {
+    None
+}
+
+#[cfg(unix)]
+mod tty {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read, Write};
+    use std::os::unix::io::AsRawFd;
+
+    use super::QUERY_TIMEOUT_DECISECONDS;
+
+    /// The controlling terminal, in noncanonical mode without echo, so that the reply to a query
+    /// can be read without the user seeing it. A read times out after QUERY_TIMEOUT_DECISECONDS.
+    /// The original mode is restored when the Tty is dropped.
+    pub struct Tty {
+        file: File,
+        original_termios: libc::termios,
+    }
+
+    impl Tty {
+        pub fn open() -> Option<Self> {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")
+                .ok()?;
+            let fd = file.as_raw_fd();
+            // A process that is not in the foreground of the terminal (e.g. `git log -p &`) would
+            // be stopped on reading from it, and the foreground process would receive the reply.
+            if unsafe { libc::tcgetpgrp(fd) != libc::getpgrp() } {
+                return None;
+            }
+            let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+            if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+                return None;
+            }
+            let original_termios = termios;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 0;
+            termios.c_cc[libc::VTIME] = QUERY_TIMEOUT_DECISECONDS;
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+                return None;
+            }
+            Some(Self {
+                file,
+                original_termios,
+            })
+        }
+    }
+
+    impl Read for Tty {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.file.read(buf)
+        }
+    }
+
+    impl Write for Tty {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.file.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    impl Drop for Tty {
+        fn drop(&mut self) {
+            unsafe {
+                // Discard any input that has not been read, such as a late reply.
+                libc::tcsetattr(
+                    self.file.as_raw_fd(),
+                    libc::TCSAFLUSH,
+                    &self.original_termios,
+                )
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::{is_light_background_from_colorfgbg, query_is_light_background};
+
+    /// A stand-in for a pseudo-terminal, which answers a query with a canned reply. Once the reply
+    /// has been read, a read returns 0 bytes, as a read from the tty does when it times out.
+    struct FakeTerminal {
+        reply: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl FakeTerminal {
+        fn new(reply: &str) -> Self {
+            Self {
+                reply: Cursor::new(reply.as_bytes().to_vec()),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for FakeTerminal {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.written.is_empty() {
+                return Ok(0);
+            }
+            self.reply.read(buf)
+        }
+    }
+
+    impl Write for FakeTerminal {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_query_is_light_background() {
+        let query = |reply| {
+            let mut terminal = FakeTerminal::new(reply);
+            let is_light_background = query_is_light_background(&mut terminal);
+            assert_eq!(terminal.written, b"\x1b]11;?\x07\x1b[c");
+            is_light_background
+        };
+        assert_eq!(
+            query("\x1b]11;rgb:ffff/ffff/ffff\x07\x1b[?62;c"),
+            Some(true)
+        );
+        assert_eq!(query("\x1b]11;rgb:fdfd/f6f6/e3e3\x1b\\"), Some(true));
+        assert_eq!(query("\x1b]11;rgb:1d1d/1f1f/2121\x07"), Some(false));
+        assert_eq!(query("\x1b]11;rgb:00/2b/36\x07"), Some(false));
+        assert_eq!(query("\x1b]11;rgb:ffff/ffff/ffff"), None);
+        assert_eq!(query("\x1b]11;rgb:ffff/ffff\x07"), None);
+        assert_eq!(query(""), None);
+        // The terminal only answers the DA1 query.
+        assert_eq!(query("\x1b[?62;c"), None);
+        // A key typed by the user is not taken as the start of a reply.
+        assert_eq!(query("x\x1b]11;rgb:ffff/ffff/ffff\x07"), None);
+    }
+
+    #[test]
+    fn test_query_stops_reading_at_non_reply_byte() {
+        let mut terminal = FakeTerminal::new("xyz");
+        assert_eq!(query_is_light_background(&mut terminal), None);
+        assert_eq!(terminal.reply.position(), 1);
+    }
+
+    #[test]
+    fn test_is_light_background_from_colorfgbg() {
+        assert_eq!(is_light_background_from_colorfgbg("15;0"), Some(false));
+        assert_eq!(is_light_background_from_colorfgbg("0;15"), Some(true));
+        assert_eq!(
+            is_light_background_from_colorfgbg("0;default;7"),
+            Some(true)
+        );
+        assert_eq!(is_light_background_from_colorfgbg("7;8"), Some(false));
+        assert_eq!(is_light_background_from_colorfgbg("default"), None);
+    }
+}
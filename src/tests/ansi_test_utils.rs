@@ -2,6 +2,7 @@
 pub mod ansi_test_utils {
     use ansi_term;
     use console::strip_ansi_codes;
+    use git_delta::parse::State;
 
     use crate::config::Config;
     use crate::paint;
@@ -100,16 +101,18 @@ pub mod ansi_test_utils {
             is_syntax_highlighted: true,
             ..Style::new()
         };
-        painter.set_syntax(Some(language_extension));
-        painter.set_highlighter();
+        painter.set_syntaxes(language_extension, language_extension);
+        painter.set_highlighters();
         let lines = vec![line];
-        let syntax_style_sections = painter.highlighter.highlight(line, &config.syntax_set);
+        let syntax_style_sections = painter.plus_highlighter.highlight(line, &config.syntax_set);
         paint::Painter::paint_lines(
             vec![syntax_style_sections],
             vec![vec![(syntax_highlighted_style, lines[0])]],
+            &State::HunkZero,
             &mut output_buffer,
             config,
-            "",
+            &mut None,
+            &["".to_string()],
             config.null_style,
             config.null_style,
             None,
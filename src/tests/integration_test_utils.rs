@@ -14,6 +14,27 @@ pub mod integration_test_utils {
     pub fn get_command_line_options() -> cli::Opt {
         let mut opt = cli::Opt::from_iter(Vec::<OsString>::new());
         opt.theme = None; // TODO: Why does opt.theme have the value Some("")?
+        opt.detect_dark_light = "never".to_string();
+        opt.true_color = "never".to_string();
+        opt.color = "always".to_string();
+        opt
+    }
+
+    /// Return the options for a command line, with the values of options not given on it taken
+    /// from the active features.
+    pub fn make_options_from_args(args: &[&str]) -> cli::Opt {
+        let args = std::iter::once("delta").chain(args.iter().copied());
+        let arg_matches = cli::Opt::clap().get_matches_from(args);
+        let mut opt = cli::Opt::from_clap(&arg_matches);
+        cli::set_options(&mut opt, &arg_matches, None);
+        opt.theme = None;
+        opt.detect_dark_light = "never".to_string();
+        if arg_matches.occurrences_of("true-color") == 0 {
+            opt.true_color = "never".to_string();
+        }
+        if arg_matches.occurrences_of("color") == 0 {
+            opt.color = "always".to_string();
+        }
         opt
     }
 
@@ -143,8 +143,7 @@ mod tests {
             DIFF_WITH_UNRECOGNIZED_PRECEDING_MATERIAL_1,
             DIFF_WITH_UNRECOGNIZED_PRECEDING_MATERIAL_2,
         ] {
-            let mut options = integration_test_utils::get_command_line_options();
-            options.color_only = true;
+            let options = integration_test_utils::make_options_from_args(&["--color-only"]);
             let (output, _) = integration_test_utils::run_delta(input, options);
             assert_eq!(strip_ansi_codes(&output), input);
             assert_ne!(output, input);
@@ -155,19 +154,223 @@ mod tests {
     fn test_diff_with_merge_conflict_is_not_truncated() {
         let options = integration_test_utils::get_command_line_options();
         let (output, _) = integration_test_utils::run_delta(DIFF_WITH_MERGE_CONFLICT, options);
-        // TODO: The + in the first column is being removed.
-        assert!(strip_ansi_codes(&output).contains("+>>>>>>> Stashed changes"));
+        assert!(strip_ansi_codes(&output).contains("\n  >>>>>>> Stashed changes\n"));
         assert_eq!(output.split('\n').count(), 47);
     }
 
     #[test]
-    fn test_diff_with_merge_conflict_is_passed_on_unchanged_under_color_only() {
+    fn test_combined_diff_keeps_prefix_column_for_each_parent() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.keep_plus_minus_markers = true;
+        let (output, _) = integration_test_utils::run_delta(DIFF_WITH_MERGE_CONFLICT, options);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\n++>>>>>>> Stashed changes\n"));
+        assert!(output.contains("\n +test: unit-test end-to-end-test\n"));
+        assert!(output.contains("\n+ test:\n"));
+        assert!(output.contains("\n  lint:\n"));
+    }
+
+    #[test]
+    fn test_combined_diff_hunk_header_and_file_header() {
+        let options = integration_test_utils::get_command_line_options();
+        let (output, _) = integration_test_utils::run_delta(DIFF_WITH_MERGE_CONFLICT, options);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nMakefile\n"));
+        assert!(output.contains("\n build "));
+        assert!(!output.contains("@@"));
+    }
+
+    #[test]
+    fn test_combined_diff_lines_are_classified_per_parent() {
         let mut options = integration_test_utils::get_command_line_options();
-        options.color_only = true;
+        options.theme = Some("none".to_string());
+        let (output, config) =
+            integration_test_utils::run_delta(COMBINED_DIFF_WITH_REMOVED_LINES, options);
+        let find_line = |text: &str| {
+            output
+                .lines()
+                .find(|line| strip_ansi_codes(line) == text)
+                .unwrap()
+        };
+        let minus_style_prefix = config.minus_style.ansi_term_style.prefix().to_string();
+        let plus_style_prefix = config.plus_style.ansi_term_style.prefix().to_string();
+        // Removed from the merge result, relative to the first and second parent respectively.
+        assert!(find_line("      let a = 100;").starts_with(&minus_style_prefix));
+        assert!(find_line("      let a = 10;").starts_with(&minus_style_prefix));
+        // Added relative to both parents.
+        assert!(find_line("      let a = 110;").starts_with(&plus_style_prefix));
+        assert!(find_line("      let c = 3;").starts_with(&plus_style_prefix));
+        assert!(!find_line("      let b = 2;").starts_with(&plus_style_prefix));
+    }
+
+    #[test]
+    fn test_diff_with_merge_conflict_is_passed_on_unchanged_under_color_only() {
+        let options = integration_test_utils::make_options_from_args(&["--color-only"]);
         let (output, _) = integration_test_utils::run_delta(DIFF_WITH_MERGE_CONFLICT, options);
         assert_eq!(strip_ansi_codes(&output), DIFF_WITH_MERGE_CONFLICT);
     }
 
+    #[test]
+    fn test_syntax_is_detected_from_shebang_when_file_has_no_extension() {
+        let get_code_lines = |input: &str| {
+            let options = integration_test_utils::get_command_line_options();
+            let (output, _) = integration_test_utils::run_delta(input, options);
+            output
+                .lines()
+                .filter(|line| {
+                    let line = strip_ansi_codes(line);
+                    line.contains("python") || line.contains("class X") || line.contains("pass")
+                })
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let with_extension = get_code_lines(&ADDED_SCRIPT.replace("bin/run", "bin/run.py"));
+        let without_extension = get_code_lines(ADDED_SCRIPT);
+        let as_plain_text = get_code_lines(&ADDED_SCRIPT.replace("bin/run", "bin/run.txt"));
+        assert_eq!(without_extension.len(), 3);
+        assert_eq!(without_extension, with_extension);
+        assert_ne!(without_extension, as_plain_text);
+        assert_eq!(
+            strip_ansi_codes(&without_extension.join("\n")),
+            strip_ansi_codes(&as_plain_text.join("\n"))
+        );
+        // A shebang is only recognized on the first line of the file.
+        let not_at_start = |input: &str| input.replace("@@ -0,0 +1,3 @@", "@@ -4,0 +5,3 @@");
+        assert_eq!(
+            get_code_lines(&not_at_start(ADDED_SCRIPT)),
+            get_code_lines(&not_at_start(
+                &ADDED_SCRIPT.replace("bin/run", "bin/run.txt")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_renamed_file_with_changed_extension_is_highlighted_with_both_syntaxes() {
+        let get_code_lines = |input: &str| {
+            let mut options = integration_test_utils::get_command_line_options();
+            options.minus_style = "syntax auto".to_string();
+            let (output, _) = integration_test_utils::run_delta(input, options);
+            output
+                .lines()
+                .filter(|line| strip_ansi_codes(line).contains("f()"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let renamed = get_code_lines(RENAMED_FILE_WITH_CHANGED_EXTENSION);
+        let as_rust = get_code_lines(&RENAMED_FILE_WITH_CHANGED_EXTENSION.replace(".py", ".rs"));
+        let as_python = get_code_lines(&RENAMED_FILE_WITH_CHANGED_EXTENSION.replace(".rs", ".py"));
+        assert_eq!(renamed.len(), 2);
+        assert_eq!(renamed[0], as_rust[0]);
+        assert_eq!(renamed[1], as_python[1]);
+        assert_ne!(as_rust[0], as_python[0]);
+    }
+
+    #[test]
+    fn test_full_file_highlighting_highlights_hunk_in_context_of_file() {
+        let dir = std::env::temp_dir().join(format!(
+            "delta-test-full-file-highlighting-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let minus_file = dir.join("a.rs");
+        let plus_file = dir.join("b.rs");
+        std::fs::write(&minus_file, "/*\n a comment\n more\n*/\nfn f() {}\n").unwrap();
+        std::fs::write(&plus_file, "/*\n a comment\n changed\n*/\nfn f() {}\n").unwrap();
+        let header = format!(
+            "--- {}\n+++ {}\n",
+            minus_file.display(),
+            plus_file.display()
+        );
+        let hunk = format!("{}@@ -3 +3 @@\n- more\n+ changed\n", header);
+        let hunk_from_file_start = format!(
+            "{}@@ -1,3 +1,3 @@\n /*\n  a comment\n- more\n+ changed\n",
+            header
+        );
+        let get_plus_line = |input: &str, full_file_highlighting: bool| {
+            let mut options = integration_test_utils::get_command_line_options();
+            options.full_file_highlighting = full_file_highlighting;
+            let (output, _) = integration_test_utils::run_delta(input, options);
+            output
+                .lines()
+                .find(|line| strip_ansi_codes(line).contains("changed"))
+                .unwrap()
+                .to_string()
+        };
+        let in_context = get_plus_line(&hunk_from_file_start, false);
+        assert_eq!(get_plus_line(&hunk, true), in_context);
+        assert_ne!(get_plus_line(&hunk, false), in_context);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_full_file_highlighting_continues_from_previous_hunk() {
+        let dir = std::env::temp_dir().join(format!(
+            "delta-test-full-file-highlighting-continues-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let minus_file = dir.join("a.rs");
+        let plus_file = dir.join("b.rs");
+        std::fs::write(&minus_file, "let x = 1;\n/*\n a comment\n more\n*/\n").unwrap();
+        std::fs::write(&plus_file, "let x = 2;\n/*\n a comment\n changed\n*/\n").unwrap();
+        let header = format!(
+            "--- {}\n+++ {}\n",
+            minus_file.display(),
+            plus_file.display()
+        );
+        let two_hunks = format!(
+            "{}@@ -1 +1 @@\n-let x = 1;\n+let x = 2;\n@@ -4 +4 @@\n- more\n+ changed\n",
+            header
+        );
+        let one_hunk = format!(
+            "{}@@ -1,4 +1,4 @@\n-let x = 1;\n+let x = 2;\n /*\n  a comment\n- more\n+ changed\n",
+            header
+        );
+        let get_plus_line = |input: &str, full_file_highlighting: bool| {
+            let mut options = integration_test_utils::get_command_line_options();
+            options.full_file_highlighting = full_file_highlighting;
+            let (output, _) = integration_test_utils::run_delta(input, options);
+            output
+                .lines()
+                .find(|line| strip_ansi_codes(line).contains("changed"))
+                .unwrap()
+                .to_string()
+        };
+        let in_context = get_plus_line(&one_hunk, false);
+        assert_eq!(get_plus_line(&two_hunks, true), in_context);
+        assert_ne!(get_plus_line(&two_hunks, false), in_context);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diff_stat_is_rendered() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.width = Some("80".to_string());
+        let (output, config) = integration_test_utils::run_delta(GIT_SHOW_WITH_STAT, options);
+        let stripped_output = strip_ansi_codes(&output);
+        assert!(stripped_output.contains("\n src/delta.rs | 12 ++++++++----\n"));
+        assert!(stripped_output.contains("\n src/main.rs  |  1 +\n"));
+        assert!(stripped_output.contains("\n 2 files changed, 9 insertions(+), 4 deletions(-)\n"));
+        assert!(output.contains(&format!(
+            " {} | 12 {}",
+            config.file_style.ansi_term_style.paint("src/delta.rs"),
+            ansi_term::ANSIStrings(&[
+                config.plus_style.ansi_term_style.paint("++++++++"),
+                config.minus_style.ansi_term_style.paint("----"),
+            ]),
+        )));
+    }
+
+    #[test]
+    fn test_diff_stat_path_wraps_at_decorations_width() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.width = Some("28".to_string());
+        let (output, _) = integration_test_utils::run_delta(GIT_SHOW_WITH_STAT, options);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\n src/\n delta.rs  | 12 ++++++++----\n"));
+        assert!(output.contains("\n src/\n main.rs   |  1 +\n"));
+    }
+
     #[test]
     fn test_submodule_contains_untracked_content() {
         let options = integration_test_utils::get_command_line_options();
@@ -946,6 +1149,24 @@ src/align.rs
         ));
     }
 
+    #[test]
+    fn test_color_never_keeps_layout_and_marks_emphasis_with_attributes() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.color = "never".to_string();
+        options.line_numbers = true;
+        let (output, _) = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, options);
+        let sgr_color_parameter =
+            regex::Regex::new(r"\x1b\[(?:[0-9;]*;)?(?:3[0-9]|4[0-9]|9[0-7]|10[0-7])[;m]").unwrap();
+        assert!(!sgr_color_parameter.is_match(&output));
+        // The emphasized sections are shown in reverse video.
+        assert!(output.contains("\x1b[7m"));
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nsrc/align.rs\n"));
+        assert!(output.contains("-                let (left, diag, up) = ("));
+        assert!(output.contains("+                let (left, diag, up) =\n"));
+        assert!(output.contains(" 71 ⋮ 71 │"));
+    }
+
     const GIT_DIFF_SINGLE_HUNK: &str = "\
 commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
 Author: Dan Davison <dandavison7@gmail.com>
@@ -1081,6 +1302,31 @@ index 0000000..8c55b7d
 +class X:
 +    pass";
 
+    const ADDED_SCRIPT: &str = "\
+diff --git a/bin/run b/bin/run
+new file mode 100755
+index 0000000..8c55b7d
+--- /dev/null
++++ b/bin/run
+@@ -0,0 +1,3 @@
++#!/usr/bin/env python
++class X:
++    pass
+";
+
+    const RENAMED_FILE_WITH_CHANGED_EXTENSION: &str = "\
+diff --git a/src/f.rs b/src/f.py
+similarity index 50%
+rename from src/f.rs
+rename to src/f.py
+index 1f3f7b1..4c4e7c2 100644
+--- a/src/f.rs
++++ b/src/f.py
+@@ -1 +1 @@
+-fn f() -> u8 { 0 }
++def f(): return 0
+";
+
     const ADDED_FILE_EXPECTED_OUTPUT: &str = "\
 commit d28dc1ac57e53432567ec5bf19ad49ff90f0f7a5
 Author: Dan Davison <dandavison7@gmail.com>
@@ -1327,6 +1573,44 @@ index cba6064..ba1a4de 100644
              .ok()
 ";
 
+    const GIT_SHOW_WITH_STAT: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed May 27 13:35:16 2020 -0400
+
+    Paint diffstat lines
+
+ src/delta.rs | 12 ++++++++----
+ src/main.rs  |  1 +
+ 2 files changed, 9 insertions(+), 4 deletions(-)
+
+diff --git a/src/main.rs b/src/main.rs
+index 9f2b9d4..bbf1e9d 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,4 @@
+ mod align;
++mod diff_stat;
+ mod bat;
+ mod cli;
+";
+
+    const COMBINED_DIFF_WITH_REMOVED_LINES: &str = "\
+diff --cc m.rs
+index cbb0046,76b71f3..e1ff98c
+--- a/m.rs
++++ b/m.rs
+@@@ -1,5 -1,5 +1,6 @@@
+  fn main() {
+-     let a = 100;
+ -    let a = 10;
+++    let a = 110;
+      let b = 2;
+++    let c = 3;
+      println!(\"{}\", a + b);
+  }
+";
+
     const DIFF_WITH_MERGE_CONFLICT: &str = r#"
 diff --cc Makefile
 index 759070d,3daf9eb..0000000
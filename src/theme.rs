@@ -1,5 +1,9 @@
-use syntect::highlighting::ThemeSet;
+use syntect::highlighting::{Theme, ThemeSet};
 
+use crate::color;
+
+/// Themes that are light although their background color does not say so: the ANSI themes encode
+/// a palette index, rather than an RGB value, in their colors.
 const LIGHT_THEMES: [&str; 5] = [
     "GitHub",
     "Monokai Extended Light",
@@ -11,8 +15,26 @@ const LIGHT_THEMES: [&str; 5] = [
 pub const DEFAULT_LIGHT_THEME: &str = "GitHub";
 pub const DEFAULT_DARK_THEME: &str = "Monokai Extended";
 
-pub fn is_light_theme(theme: &str) -> bool {
-    LIGHT_THEMES.contains(&theme)
+/// Return whether a theme is intended for a light terminal background: either it is one of
+/// LIGHT_THEMES, or its background color is light.
+pub fn is_light_theme(theme_name: &str, theme_set: &ThemeSet) -> bool {
+    LIGHT_THEMES.contains(&theme_name)
+        || theme_set
+            .themes
+            .get(theme_name)
+            .is_some_and(has_light_background)
+}
+
+fn has_light_background(theme: &Theme) -> bool {
+    match theme.settings.background {
+        // An alpha of 0 means that the color is a palette index rather than an RGB value.
+        Some(color) if color.a > 0 => color::is_light_rgb(
+            color.r as f64 / 255.0,
+            color.g as f64 / 255.0,
+            color.b as f64 / 255.0,
+        ),
+        _ => false,
+    }
 }
 
 pub fn is_no_syntax_highlighting_theme_name(theme_name: &str) -> bool {
@@ -30,7 +52,9 @@ pub fn is_no_syntax_highlighting_theme_name(theme_name: &str) -> bool {
 /// 1. The theme is specified by the `--theme` option. If this isn't supplied then it is specified by the `BAT_PAGER` environment variable.
 /// 2. Light vs dark mode is specified by the `--light` or `--dark` options. If these aren't supplied then it is inferred from the chosen theme.
 ///
-/// In the absence of other factors, the default assumes a dark terminal background.
+/// In the absence of other factors, the light vs dark mode is that of the terminal background, if
+/// it can be detected (see --detect-dark-light), and the default assumes a dark terminal
+/// background otherwise.
 ///
 /// Specifically, the rules are as follows:
 ///
@@ -52,8 +76,8 @@ pub fn get_is_light_mode_and_theme_name(
     let bat_theme_env_var = valid_theme_name_or_none(bat_theme_env_var, theme_set);
     match (theme_arg, bat_theme_env_var, light_mode_arg) {
         (None, None, false) => (false, DEFAULT_DARK_THEME.to_string()),
-        (Some(theme_name), _, false) => (is_light_theme(&theme_name), theme_name),
-        (None, Some(theme_name), false) => (is_light_theme(&theme_name), theme_name),
+        (Some(theme_name), _, false) => (is_light_theme(&theme_name, theme_set), theme_name),
+        (None, Some(theme_name), false) => (is_light_theme(&theme_name, theme_set), theme_name),
         (None, None, true) => (true, DEFAULT_LIGHT_THEME.to_string()),
         (Some(theme_name), _, is_light_mode) => (is_light_mode, theme_name),
         (None, Some(theme_name), is_light_mode) => (is_light_mode, theme_name),
@@ -73,3 +97,36 @@ fn valid_theme_name_or_none(theme_name: Option<&String>, theme_set: &ThemeSet) -
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use syntect::highlighting::{Color, Theme, ThemeSet};
+
+    use super::is_light_theme;
+    use crate::bat::assets::HighlightingAssets;
+
+    #[test]
+    fn test_is_light_theme() {
+        let mut theme_set = HighlightingAssets::from_binary().theme_set;
+        assert!(is_light_theme("GitHub", &theme_set));
+        assert!(is_light_theme("Solarized (light)", &theme_set));
+        assert!(is_light_theme("ansi-light", &theme_set));
+        assert!(!is_light_theme("Monokai Extended", &theme_set));
+        assert!(!is_light_theme("ansi-dark", &theme_set));
+
+        let make_theme = |r, g, b| {
+            let mut theme = Theme::default();
+            theme.settings.background = Some(Color { r, g, b, a: 0xff });
+            theme
+        };
+        theme_set
+            .themes
+            .insert("custom-light".to_string(), make_theme(0xee, 0xee, 0xdd));
+        theme_set
+            .themes
+            .insert("custom-dark".to_string(), make_theme(0x1d, 0x1f, 0x21));
+        assert!(is_light_theme("custom-light", &theme_set));
+        assert!(!is_light_theme("custom-dark", &theme_set));
+        assert!(!is_light_theme("none", &ThemeSet::new()));
+    }
+}
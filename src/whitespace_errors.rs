@@ -0,0 +1,112 @@
+/// Return the byte ranges of whitespace errors in the content of an added line (i.e. the line
+/// without its -/+/space prefix), as git does with its default `core.whitespace` setting. Two
+/// kinds of error are detected:
+///
+/// 1. Trailing whitespace (blank-at-eol), including a carriage return at the end of the line.
+/// 2. A space before a tab in the indentation (space-before-tab). The error extends from the
+///    first such space to the last tab of the indentation.
+///
+/// The detection is performed on the raw line, since it depends on the tabs, but the returned
+/// ranges refer to the line after preparation for painting, i.e. with the prefix replaced by a
+/// single space and tabs expanded to `tab_width` spaces.
+pub fn get_whitespace_error_ranges(content: &str, tab_width: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let indentation_end = content.len() - content.trim_start_matches(&[' ', '\t'][..]).len();
+    let trailing_start = content.trim_end_matches(&[' ', '\t', '\r'][..]).len();
+    let indentation = &content[..indentation_end.min(trailing_start)];
+    if let (Some(first_space), Some(last_tab)) = (indentation.find(' '), indentation.rfind('\t')) {
+        if first_space < last_tab {
+            ranges.push((first_space, last_tab + 1));
+        }
+    }
+    if trailing_start < content.len() {
+        ranges.push((trailing_start, content.len()));
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            (
+                get_prepared_offset(content, start, tab_width),
+                get_prepared_offset(content, end, tab_width),
+            )
+        })
+        .collect()
+}
+
+/// Map a byte offset in the content of a line (i.e. the line without its prefix columns) to the
+/// corresponding offset in the line as prepared for painting by `delta::prepare`.
+pub fn get_prepared_offset(content: &str, offset: usize, tab_width: usize) -> usize {
+    let n_tabs = content[..offset].matches('\t').count();
+    if tab_width > 0 {
+        1 + offset + n_tabs * (tab_width - 1)
+    } else {
+        1 + offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_no_whitespace_errors() {
+        assert_eq!(get_whitespace_error_ranges("", 4), vec![]);
+        assert_eq!(get_whitespace_error_ranges("\tlet x = 1;", 4), vec![]);
+        assert_eq!(get_whitespace_error_ranges("    let x = 1;", 4), vec![]);
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        assert_eq!(get_whitespace_error_ranges("x  ", 4), vec![(2, 4)]);
+        assert_eq!(get_whitespace_error_ranges("x \t", 4), vec![(2, 7)]);
+        assert_eq!(get_whitespace_error_ranges("  ", 4), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_trailing_carriage_return() {
+        assert_eq!(get_whitespace_error_ranges("x\r", 4), vec![(2, 3)]);
+        assert_eq!(get_whitespace_error_ranges("x \r", 4), vec![(2, 4)]);
+    }
+
+    #[test]
+    fn test_space_before_tab() {
+        assert_eq!(get_whitespace_error_ranges(" \tx", 4), vec![(1, 6)]);
+        assert_eq!(get_whitespace_error_ranges("\t \tx", 4), vec![(5, 10)]);
+        assert_eq!(get_whitespace_error_ranges(" \tx", 0), vec![(1, 3)]);
+        assert_eq!(
+            get_whitespace_error_ranges(" \tx ", 8),
+            vec![(1, 10), (11, 12)]
+        );
+    }
+
+    #[test]
+    fn test_tab_before_space_is_not_an_error() {
+        assert_eq!(get_whitespace_error_ranges("\t  x", 4), vec![]);
+        assert_eq!(get_whitespace_error_ranges("\t  x", 0), vec![]);
+    }
+
+    #[test]
+    fn test_whitespace_errors_are_painted_in_added_lines_only() {
+        let mut options = integration_test_utils::get_command_line_options();
+        options.whitespace_error_style = "normal red".to_string();
+        let (output, config) = integration_test_utils::run_delta(WHITESPACE_ERRORS_DIFF, options);
+        let error_style = config.whitespace_error_style.ansi_term_style;
+        let lines: Vec<&str> = output.lines().collect();
+        let added_line = lines.iter().find(|line| line.contains("trailing")).unwrap();
+        assert!(added_line.contains(&format!("{}   ", error_style.prefix())));
+        let removed_line = lines.iter().find(|line| line.contains("removed")).unwrap();
+        assert!(!removed_line.contains(&error_style.prefix().to_string()));
+    }
+
+    const WHITESPACE_ERRORS_DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+index 8e1e71d..f7fa5b8 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-removed
++trailing\x20\x20\x20
+";
+}
@@ -0,0 +1,263 @@
+use crate::style::Style;
+
+/// The emph ranges and alignment of the buffered minus and plus lines of a word diff. These are
+/// known from the word-diff markers, instead of being inferred by edits::infer_edits.
+#[derive(Default)]
+pub struct WordDiffData {
+    pub minus_line_emph_ranges: Vec<Vec<(usize, usize)>>,
+    pub plus_line_emph_ranges: Vec<Vec<(usize, usize)>>,
+    pub line_alignment: Vec<(Option<usize>, Option<usize>)>,
+}
+
+pub fn is_unchanged(word_diff_line: &[(char, String)]) -> bool {
+    word_diff_line.iter().all(|(kind, _)| *kind == ' ')
+}
+
+/// Return the text of the old (kind = '-') or new (kind = '+') version of the line, together
+/// with the byte ranges of the changed tokens within it. Return None if that version of the line
+/// is empty, as is the case for a line that has been added or removed entirely. If the line has
+/// no unchanged text then it is not useful to emphasize the changed tokens, and no ranges are
+/// returned.
+pub fn get_line_and_emph_ranges(
+    word_diff_line: &[(char, String)],
+    kind: char,
+) -> Option<(String, Vec<(usize, usize)>)> {
+    let mut line = String::new();
+    let mut emph_ranges = Vec::new();
+    let mut has_unchanged_text = false;
+    for (token_kind, text) in word_diff_line {
+        if *token_kind == kind {
+            emph_ranges.push((line.len(), line.len() + text.len()));
+            line.push_str(text);
+        } else if *token_kind == ' ' {
+            has_unchanged_text = has_unchanged_text || !text.trim().is_empty();
+            line.push_str(text);
+        }
+    }
+    if line.is_empty() {
+        None
+    } else if has_unchanged_text {
+        Some((line, emph_ranges))
+    } else {
+        Some((line, Vec::new()))
+    }
+}
+
+/// Return the diff style sections of each line, given the byte ranges of its emph sections.
+pub fn get_diff_style_sections_for_lines<'a>(
+    lines: &'a [String],
+    emph_ranges: &[Vec<(usize, usize)>],
+    style: Style,
+    emph_style: Style,
+    non_emph_style: Style,
+) -> Vec<Vec<(Style, &'a str)>> {
+    lines
+        .iter()
+        .zip(emph_ranges.iter())
+        .map(|(line, emph_ranges)| {
+            get_diff_style_sections(line, emph_ranges, style, emph_style, non_emph_style)
+        })
+        .collect()
+}
+
+fn get_diff_style_sections<'a>(
+    line: &'a str,
+    emph_ranges: &[(usize, usize)],
+    style: Style,
+    emph_style: Style,
+    non_emph_style: Style,
+) -> Vec<(Style, &'a str)> {
+    if emph_ranges.is_empty() {
+        return vec![(style, line)];
+    }
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    for (start, end) in emph_ranges {
+        if offset < *start {
+            sections.push((non_emph_style, &line[offset..*start]));
+        }
+        if start < end {
+            sections.push((emph_style, &line[*start..*end]));
+        }
+        offset = *end;
+    }
+    if offset < line.len() {
+        sections.push((non_emph_style, &line[offset..]));
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_get_line_and_emph_ranges() {
+        // a [-b-]{+cc+} d
+        let line = vec![
+            (' ', "a ".to_string()),
+            ('-', "b".to_string()),
+            ('+', "cc".to_string()),
+            (' ', " d".to_string()),
+        ];
+        assert_eq!(
+            get_line_and_emph_ranges(&line, '-'),
+            Some(("a b d".to_string(), vec![(2, 3)]))
+        );
+        assert_eq!(
+            get_line_and_emph_ranges(&line, '+'),
+            Some(("a cc d".to_string(), vec![(2, 4)]))
+        );
+        let line = vec![('+', "added".to_string())];
+        assert_eq!(get_line_and_emph_ranges(&line, '-'), None);
+        assert_eq!(
+            get_line_and_emph_ranges(&line, '+'),
+            Some(("added".to_string(), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_get_diff_style_sections() {
+        let (style, emph_style, non_emph_style) = (
+            Style::new(),
+            Style {
+                is_emph: true,
+                ..Style::new()
+            },
+            Style {
+                is_syntax_highlighted: true,
+                ..Style::new()
+            },
+        );
+        assert_eq!(
+            get_diff_style_sections("a cc d", &[(2, 4)], style, emph_style, non_emph_style),
+            vec![
+                (non_emph_style, "a "),
+                (emph_style, "cc"),
+                (non_emph_style, " d")
+            ]
+        );
+        assert_eq!(
+            get_diff_style_sections("a", &[], style, emph_style, non_emph_style),
+            vec![(style, "a")]
+        );
+    }
+
+    #[test]
+    fn test_plain_and_porcelain_word_diffs_are_displayed_as_minus_and_plus_lines() {
+        for input in &[PLAIN_WORD_DIFF, PORCELAIN_WORD_DIFF] {
+            let options = integration_test_utils::get_command_line_options();
+            let (output, config) = integration_test_utils::run_delta(input, options);
+            let lines: Vec<&str> = output.lines().collect();
+            let stripped_lines: Vec<String> = lines
+                .iter()
+                .map(|line| strip_ansi_codes(line).to_string())
+                .collect();
+            let find_line = |text: &str| {
+                lines[stripped_lines
+                    .iter()
+                    .position(|line| line == text)
+                    .unwrap_or_else(|| panic!("{:?} not found in {:?}", text, stripped_lines))]
+            };
+            assert!(find_line(" The quick brown fox")
+                .starts_with(&config.zero_style.ansi_term_style.prefix().to_string()));
+            let emph_prefix = config.minus_emph_style.ansi_term_style.prefix().to_string();
+            assert!(
+                find_line(" jumps over the lazy dog.").contains(&format!("{}lazy", emph_prefix))
+            );
+            let emph_prefix = config.plus_emph_style.ansi_term_style.prefix().to_string();
+            assert!(find_line(" jumps over the sleepy dog.")
+                .contains(&format!("{}sleepy", emph_prefix)));
+            find_line(" A removed line.");
+            find_line(" An added line.");
+            assert!(!stripped_lines
+                .iter()
+                .any(|line| line.contains('~') || line.contains("[-") || line.contains("{+")));
+        }
+    }
+
+    #[test]
+    fn test_plain_word_diff_of_markdown_list() {
+        let options = integration_test_utils::get_command_line_options();
+        let (output, _) = integration_test_utils::run_delta(MARKDOWN_LIST_PLAIN_WORD_DIFF, options);
+        let stripped_lines: Vec<String> = output
+            .lines()
+            .map(|line| strip_ansi_codes(line).to_string())
+            .collect();
+        assert!(stripped_lines.contains(&" - item two".to_string()));
+        assert!(stripped_lines.contains(&" - item three".to_string()));
+        assert!(!stripped_lines
+            .iter()
+            .any(|line| line.contains("[-") || line.contains("{+")));
+    }
+
+    #[test]
+    fn test_format_patch_trailer_is_not_a_word_diff() {
+        let options = integration_test_utils::get_command_line_options();
+        let (output, _) = integration_test_utils::run_delta(FORMAT_PATCH, options);
+        let stripped_lines: Vec<String> = output
+            .lines()
+            .map(|line| strip_ansi_codes(line).to_string())
+            .collect();
+        assert!(stripped_lines.contains(&" a".to_string()));
+        assert!(stripped_lines.contains(&" b".to_string()));
+        assert!(stripped_lines.contains(&"2.30.0".to_string()));
+    }
+
+    const MARKDOWN_LIST_PLAIN_WORD_DIFF: &str = "\
+diff --git a/doc.md b/doc.md
+index 8e1e71d..f7fa5b8 100644
+--- a/doc.md
++++ b/doc.md
+@@ -1,2 +1,2 @@
+Some items:
+- item [-two-]{+three+}
+";
+
+    const FORMAT_PATCH: &str = "\
+diff --git a/a.txt b/a.txt
+index 8e1e71d..f7fa5b8 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-a
++b
+-- 
+2.30.0
+";
+
+    const PLAIN_WORD_DIFF: &str = "\
+diff --git a/doc.md b/doc.md
+index 8e1e71d..f7fa5b8 100644
+--- a/doc.md
++++ b/doc.md
+@@ -1,3 +1,3 @@
+The quick brown fox
+jumps over the [-lazy-]{+sleepy+} dog.
+[-A removed line.-]
+{+An added line.+}
+";
+
+    const PORCELAIN_WORD_DIFF: &str = "\
+diff --git a/doc.md b/doc.md
+index 8e1e71d..f7fa5b8 100644
+--- a/doc.md
++++ b/doc.md
+@@ -1,3 +1,3 @@
+ The quick brown fox
+~
+ jumps over the\x20
+-lazy
++sleepy
+  dog.
+~
+-A removed line.
+~
++An added line.
+~
+";
+}